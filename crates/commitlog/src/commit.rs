@@ -3,11 +3,14 @@ use std::{
     ops::Range,
 };
 
-use crc32c::{Crc32cReader, Crc32cWriter};
-use spacetimedb_sats::buffer::{BufReader, Cursor, DecodeError};
+use sha2::{Digest as _, Sha256};
+use spacetimedb_sats::{
+    buffer::{BufReader, Cursor, DecodeError},
+    AlgebraicType, AlgebraicValue, ArrayValue, ProductType, ProductValue, SumType, SumValue,
+};
 
 use crate::{
-    error::ChecksumMismatch,
+    error::{ChecksumMismatch, InsufficientSpace, SchemaMismatch},
     payload::Decoder,
     segment::{CHECKSUM_ALGORITHM_CRC32C, CHECKSUM_CRC32C_LEN},
     Transaction, DEFAULT_LOG_FORMAT_VERSION,
@@ -20,6 +23,13 @@ enum Version {
     V1,
 }
 
+/// The header of an encoded [`Commit`].
+///
+/// In the current log format, the fields are stored in declaration order as
+/// little-endian integers, at the byte ranges given by [`Self::field_offsets`].
+/// The header is followed by `len` bytes of records and the checksum, see
+/// [`Commit::checksum_offset`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Header {
     pub min_tx_offset: u64,
     pub epoch: u64,
@@ -27,9 +37,37 @@ pub struct Header {
     pub len: u32,
 }
 
+/// Byte ranges of the fields of a [`Header`] within an encoded commit,
+/// as returned by [`Header::field_offsets`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeaderFieldOffsets {
+    pub min_tx_offset: Range<usize>,
+    pub epoch: Range<usize>,
+    pub n: Range<usize>,
+    pub len: Range<usize>,
+}
+
 impl Header {
     pub const LEN: usize = /* offset */ 8 + /* epoch */ 8 + /* n */ 2 + /* len */  4;
 
+    pub const MIN_TX_OFFSET_FIELD: Range<usize> = 0..8;
+    pub const EPOCH_FIELD: Range<usize> = 8..16;
+    pub const N_FIELD: Range<usize> = 16..18;
+    pub const LEN_FIELD: Range<usize> = 18..Self::LEN;
+
+    /// The byte ranges of the header fields within an encoded commit.
+    ///
+    /// This allows external tools to interpret the header bytes directly,
+    /// without going through [`Self::decode`].
+    pub fn field_offsets() -> HeaderFieldOffsets {
+        HeaderFieldOffsets {
+            min_tx_offset: Self::MIN_TX_OFFSET_FIELD,
+            epoch: Self::EPOCH_FIELD,
+            n: Self::N_FIELD,
+            len: Self::LEN_FIELD,
+        }
+    }
+
     /// Read [`Self::LEN`] bytes from `reader` and interpret them as the
     /// "header" of a [`Commit`].
     ///
@@ -91,7 +129,7 @@ impl Header {
             return Err(e);
         }
         match &mut hdr.as_slice() {
-            [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0] => Ok(None),
+            [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0] => Ok(None),
             buf => {
                 let min_tx_offset = buf.get_u64().map_err(decode_error)?;
                 let epoch = buf.get_u64().map_err(decode_error)?;
@@ -150,29 +188,114 @@ impl Commit {
         self.min_tx_offset..self.min_tx_offset + self.n as u64
     }
 
+    /// The byte offset of the checksum within an encoded commit whose records
+    /// are `records_len` bytes long.
+    ///
+    /// The checksum is a little-endian `u32` of [`Self::CHECKSUM_LEN`] bytes,
+    /// computed over all bytes preceding it.
+    pub const fn checksum_offset(records_len: usize) -> usize {
+        Header::LEN + records_len
+    }
+
     /// Length in bytes of this commit when written to the log via [`Self::write`].
     pub fn encoded_len(&self) -> usize {
         Self::FRAMING_LEN + self.records.len()
     }
 
+    /// Compute a SHA-256 hash over the logical contents of this commit,
+    /// e.g. to key it in a content-addressed store.
+    ///
+    /// The hash is computed over `min_tx_offset` as a little-endian `u64`,
+    /// followed by `n` as a little-endian `u16`, followed by the `records`.
+    /// The `epoch` and the framing of the commit, including its checksum, are
+    /// not part of the hash, so re-framing a commit doesn't change it.
+    pub fn content_hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.min_tx_offset.to_le_bytes());
+        hasher.update(self.n.to_le_bytes());
+        hasher.update(&self.records);
+
+        hasher.finalize().into()
+    }
+
     /// Serialize and write `self` to `out`.
     ///
     /// Returns the crc32 checksum of the commit on success.
     pub fn write<W: Write>(&self, out: W) -> io::Result<u32> {
-        let mut out = Crc32cWriter::new(out);
+        self.write_with::<W, Crc32cHasher>(out)
+    }
+
+    /// Like [`Self::write`], but fails without writing anything if `out`
+    /// cannot hold the whole commit.
+    ///
+    /// If the [`RemainingCapacity`] of `out` is less than
+    /// [`Self::encoded_len`], an error of kind [`io::ErrorKind::StorageFull`]
+    /// with an inner error downcastable to [`InsufficientSpace`] is returned.
+    /// This allows callers to roll over to a new segment instead of leaving a
+    /// partial commit behind.
+    pub fn try_write<W: Write + RemainingCapacity>(&self, out: W) -> io::Result<u32> {
+        let needed = self.encoded_len() as u64;
+        let available = out.remaining_capacity();
+        if needed > available {
+            return Err(io::Error::new(
+                io::ErrorKind::StorageFull,
+                InsufficientSpace { needed, available },
+            ));
+        }
+        self.write(out)
+    }
+
+    /// Like [`Self::write`], but computes the checksum using the
+    /// [`Checksummer`] `C`.
+    pub fn write_with<W: Write, C: Checksummer>(&self, out: W) -> io::Result<u32> {
+        let hdr = Header {
+            min_tx_offset: self.min_tx_offset,
+            epoch: self.epoch,
+            n: self.n,
+            len: self.records.len() as u32,
+        };
+        Self::write_framed::<W, C>(hdr, &self.records, out)
+    }
 
-        let min_tx_offset = self.min_tx_offset.to_le_bytes();
-        let epoch = self.epoch.to_le_bytes();
-        let n = self.n.to_le_bytes();
-        let len = (self.records.len() as u32).to_le_bytes();
+    /// The serialized records of this commit, i.e. the record payload without
+    /// the commit framing.
+    ///
+    /// The records are stored exactly as produced by [`crate::Encode`], which
+    /// is BSATN for the payloads provided by this crate. They can thus be
+    /// handed to another transport as is, there is no need to decode and
+    /// re-serialize them.
+    pub fn records_as_bsatn(&self) -> &[u8] {
+        &self.records
+    }
 
-        out.write_all(&min_tx_offset)?;
-        out.write_all(&epoch)?;
-        out.write_all(&n)?;
-        out.write_all(&len)?;
-        out.write_all(&self.records)?;
+    /// Frame the records of this commit anew, as if they were committed at
+    /// `min_tx_offset` within `epoch`.
+    ///
+    /// Returns the encoded commit, including a freshly computed checksum.
+    /// The records are copied unchanged.
+    pub fn reencode_framing(&self, min_tx_offset: u64, epoch: u64) -> Vec<u8> {
+        let hdr = Header {
+            min_tx_offset,
+            epoch,
+            n: self.n,
+            len: self.records.len() as u32,
+        };
+        let mut buf = Vec::with_capacity(self.encoded_len());
+        Self::write_framed::<_, Crc32cHasher>(hdr, &self.records, &mut buf)
+            .expect("writing to a `Vec` should never fail");
+        buf
+    }
 
-        let crc = out.crc32c();
+    fn write_framed<W: Write, C: Checksummer>(hdr: Header, records: &[u8], out: W) -> io::Result<u32> {
+        let mut out = ChecksumWriter::<W, C>::new(out);
+
+        out.write_all(&hdr.min_tx_offset.to_le_bytes())?;
+        out.write_all(&hdr.epoch.to_le_bytes())?;
+        out.write_all(&hdr.n.to_le_bytes())?;
+        out.write_all(&hdr.len.to_le_bytes())?;
+        out.write_all(records)?;
+
+        let crc = out.checksum();
         let mut out = out.into_inner();
         out.write_all(&crc.to_le_bytes())?;
 
@@ -193,6 +316,68 @@ impl Commit {
         Ok(commit.map(Into::into))
     }
 
+    /// Like [`Self::decode`], but also returns the verified checksum of the
+    /// commit.
+    pub fn decode_with_crc<R: Read>(reader: R) -> io::Result<Option<(Self, u32)>> {
+        let commit = StoredCommit::decode(reader)?;
+        Ok(commit.map(|commit| {
+            let crc = commit.checksum;
+            (commit.into(), crc)
+        }))
+    }
+
+    /// Decode exactly one [`Commit`] from `buf`.
+    ///
+    /// Unlike [`Self::decode`], `buf` must contain exactly the framed bytes of
+    /// one commit. If `buf` is empty or too short, an error of kind
+    /// [`io::ErrorKind::UnexpectedEof`] is returned. If bytes are left over
+    /// after the commit, an error of kind [`io::ErrorKind::InvalidData`] is
+    /// returned.
+    pub fn decode_exact(buf: &[u8]) -> io::Result<Self> {
+        let mut reader = buf;
+        let commit = Self::decode(&mut reader)?.ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        if !reader.is_empty() {
+            return Err(invalid_data(format!(
+                "{} trailing bytes after commit at offset {}",
+                reader.len(),
+                commit.min_tx_offset
+            )));
+        }
+
+        Ok(commit)
+    }
+
+    /// Like [`Self::decode`], but for logs in which each commit is padded to
+    /// a multiple of `align` bytes.
+    ///
+    /// The reader is assumed to be positioned at an `align`ed boundary. After
+    /// reading the commit, the padding up to the next boundary is consumed,
+    /// so that the reader is positioned at the start of the next commit.
+    ///
+    /// The padding must consist of zero bytes, otherwise an error of kind
+    /// [`io::ErrorKind::InvalidData`] is returned. The padding may be cut
+    /// short by EOF, e.g. if the last commit in the log was written unpadded.
+    ///
+    /// An `align` of `0` or `1` means no padding, equivalent to [`Self::decode`].
+    pub fn decode_aligned<R: Read>(mut reader: R, align: usize) -> io::Result<Option<Self>> {
+        let Some(commit) = Self::decode(&mut reader)? else {
+            return Ok(None);
+        };
+        if align > 1 {
+            let padding = (align - commit.encoded_len() % align) % align;
+            let mut buf = Vec::with_capacity(padding);
+            reader.take(padding as u64).read_to_end(&mut buf)?;
+            if buf.iter().any(|&b| b != 0) {
+                return Err(invalid_data(format!(
+                    "non-zero padding after commit at offset {}",
+                    commit.min_tx_offset
+                )));
+            }
+        }
+
+        Ok(Some(commit))
+    }
+
     /// Convert `self` into an iterator yielding [`Transaction`]s.
     ///
     /// The supplied [`Decoder`] is responsible for extracting individual
@@ -241,6 +426,128 @@ impl Commit {
             })
             .flatten()
     }
+
+    /// Like [`Self::into_transactions`], but decodes all transactions eagerly.
+    ///
+    /// The returned vector borrows neither `self` nor `de`, so it can outlive
+    /// both.
+    pub fn into_transactions_owned<D: Decoder>(
+        self,
+        version: u8,
+        from_offset: u64,
+        de: &D,
+    ) -> Vec<Result<Transaction<D::Record>, D::Error>> {
+        self.into_transactions(version, from_offset, de).collect()
+    }
+
+    /// Split `self` into two commits, the first containing the first `k`
+    /// records, and the second containing the remaining ones.
+    ///
+    /// The records are not cut in half: `de` is used to skip over the first
+    /// `k` records, as per [`Decoder::skip_record`], to find the boundary.
+    /// `version` is the log format version of the segment `self` was read
+    /// from.
+    ///
+    /// The second commit starts at `self.min_tx_offset + k`. Both commits
+    /// retain the epoch of `self`. Either may be empty if `k` is zero or
+    /// `self.n`. If `k` is greater than `self.n`, an error is returned.
+    pub fn split_at_record<D: Decoder>(self, k: u16, version: u8, de: &D) -> Result<(Commit, Commit), D::Error> {
+        if k > self.n {
+            return Err(
+                DecodeError::Other(format!("cannot split commit of {} records at record {}", self.n, k)).into(),
+            );
+        }
+        let mut reader = &self.records[..];
+        for offset in self.min_tx_offset..self.min_tx_offset + k as u64 {
+            de.skip_record(version, offset, &mut reader)?;
+        }
+        let boundary = self.records.len() - reader.len();
+
+        let mut head = self;
+        let tail = Commit {
+            min_tx_offset: head.min_tx_offset + k as u64,
+            epoch: head.epoch,
+            n: head.n - k,
+            records: head.records.split_off(boundary),
+        };
+        head.n = k;
+
+        Ok((head, tail))
+    }
+
+    /// Check that the records of `self` are rows of type `expected`, e.g.
+    /// before replaying them.
+    ///
+    /// Each record is decoded using `de`, and must be a valid value of
+    /// `expected`, i.e. have the same number of fields, each of which is a
+    /// value of the type of the respective field in `expected`. `version` is
+    /// the log format version of the segment `self` was read from.
+    ///
+    /// Returns a [`SchemaMismatch`] describing the first record which isn't a
+    /// valid value of `expected`, or which `de` fails to decode.
+    pub fn validate_schema<D>(
+        &self,
+        version: u8,
+        de: &D,
+        expected: &ProductType,
+    ) -> Result<(), SchemaMismatch<D::Error>>
+    where
+        D: Decoder<Record = ProductValue>,
+    {
+        let mut reader = &self.records[..];
+        for tx_offset in self.tx_range() {
+            let mismatch = |actual, source| SchemaMismatch {
+                tx_offset,
+                expected: expected.clone(),
+                actual,
+                source,
+            };
+            let record = de
+                .decode_record(version, tx_offset, &mut reader)
+                .map_err(|e| mismatch(None, Some(e)))?;
+            if !product_has_type(&record, expected) {
+                return Err(mismatch(AlgebraicValue::Product(record).type_of(), None));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check whether `self` and `other` contain the same records, irrespective
+    /// of their order within the commits, e.g. in tests or diagnostics.
+    ///
+    /// The records of both commits are decoded using `de`, and compared as
+    /// multisets, i.e. each record of `self` must be matched by a distinct,
+    /// equal record of `other`. `version` is the log format version of the
+    /// segments the commits were read from. The offsets and epochs of the
+    /// commits are not compared.
+    ///
+    /// Records are compared pairwise, so this is quadratic in `n`.
+    pub fn records_multiset_eq<D>(&self, other: &Commit, version: u8, de: &D) -> Result<bool, D::Error>
+    where
+        D: Decoder,
+        D::Record: PartialEq,
+    {
+        if self.n != other.n {
+            return Ok(false);
+        }
+        let decode = |commit: &Commit| {
+            let mut reader = &commit.records[..];
+            commit
+                .tx_range()
+                .map(|tx_offset| de.decode_record(version, tx_offset, &mut reader))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let mut unmatched = decode(other)?;
+        for record in decode(self)? {
+            let Some(pos) = unmatched.iter().position(|other| *other == record) else {
+                return Ok(false);
+            };
+            unmatched.swap_remove(pos);
+        }
+
+        Ok(true)
+    }
 }
 
 impl From<StoredCommit> for Commit {
@@ -297,8 +604,18 @@ impl StoredCommit {
         Self::decode_internal(reader, DEFAULT_LOG_FORMAT_VERSION)
     }
 
+    /// Like [`Self::decode`], but verifies the checksum using the
+    /// [`Checksummer`] `C`.
+    pub fn decode_with<R: Read, C: Checksummer>(reader: R) -> io::Result<Option<Self>> {
+        Self::decode_internal_with::<R, C>(reader, DEFAULT_LOG_FORMAT_VERSION)
+    }
+
     pub(crate) fn decode_internal<R: Read>(reader: R, log_format_version: u8) -> io::Result<Option<Self>> {
-        let mut reader = Crc32cReader::new(reader);
+        Self::decode_internal_with::<R, Crc32cHasher>(reader, log_format_version)
+    }
+
+    fn decode_internal_with<R: Read, C: Checksummer>(reader: R, log_format_version: u8) -> io::Result<Option<Self>> {
+        let mut reader = ChecksumReader::<R, C>::new(reader);
 
         let v = if log_format_version == 0 {
             Version::V0
@@ -311,7 +628,7 @@ impl StoredCommit {
         let mut records = vec![0; hdr.len as usize];
         reader.read_exact(&mut records)?;
 
-        let chk = reader.crc32c();
+        let chk = reader.checksum();
         let crc = decode_u32(reader.into_inner())?;
 
         if chk != crc {
@@ -341,6 +658,415 @@ impl StoredCommit {
     }
 }
 
+/// Reads [`Commit`]s from an [`io::Read`]er until EOF.
+///
+/// Decoding stops at EOF, as determined by [`Commit::decode`]. The
+/// [`Iterator`] impl yields `io::Result<Commit>`, and is fused: after
+/// yielding `None` or an error, it yields `None` indefinitely.
+///
+/// The reader is not buffered internally, so callers should supply an
+/// [`io::BufReader`] if `inner` performs system calls.
+pub struct CommitReader<R> {
+    inner: R,
+    done: bool,
+}
+
+impl<R: Read> CommitReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, done: false }
+    }
+
+    /// Read the next [`Commit`], or `None` if the reader is at EOF.
+    pub fn next_commit(&mut self) -> io::Result<Option<Commit>> {
+        if self.done {
+            return Ok(None);
+        }
+        let res = Commit::decode(&mut self.inner);
+        if !matches!(res, Ok(Some(_))) {
+            self.done = true;
+        }
+        res
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Consume `self`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Iterator for CommitReader<R> {
+    type Item = io::Result<Commit>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_commit().transpose()
+    }
+}
+
+impl<R: Read> std::iter::FusedIterator for CommitReader<R> {}
+
+/// A writer which knows how many more bytes it can accept, e.g. because it is
+/// backed by a fixed-size or preallocated buffer.
+///
+/// See [`Commit::try_write`].
+pub trait RemainingCapacity {
+    /// The number of bytes which can be written before the writer is full.
+    fn remaining_capacity(&self) -> u64;
+}
+
+impl RemainingCapacity for &mut [u8] {
+    fn remaining_capacity(&self) -> u64 {
+        self.len() as u64
+    }
+}
+
+impl<T: RemainingCapacity + ?Sized> RemainingCapacity for &mut T {
+    fn remaining_capacity(&self) -> u64 {
+        (**self).remaining_capacity()
+    }
+}
+
+/// Incremental computation of the 32-bit checksum framing [`Commit`]s.
+///
+/// The checksum is computed over the encoded commit header and records, and
+/// stored in the [`Commit::CHECKSUM_LEN`] bytes following the records.
+///
+/// The default is [`Crc32cHasher`]. Other implementations computing the same
+/// checksum, e.g. hardware-accelerated ones, can be supplied to
+/// [`Commit::write_with`] and [`StoredCommit::decode_with`] without changing
+/// the format. Implementations of a different algorithm are only understood
+/// by [`reframe`].
+pub trait Checksummer: Default {
+    /// The identifier of the algorithm, as stored in
+    /// [`crate::segment::Header::checksum_algorithm`].
+    const ALGORITHM: u8;
+
+    /// Feed `data` into the checksum.
+    fn update(&mut self, data: &[u8]);
+    /// The checksum of all data fed so far.
+    fn checksum(&self) -> u32;
+}
+
+/// The default [`Checksummer`], computing CRC32C using the `crc32c` crate.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Crc32cHasher(u32);
+
+impl Checksummer for Crc32cHasher {
+    const ALGORITHM: u8 = CHECKSUM_ALGORITHM_CRC32C;
+
+    fn update(&mut self, data: &[u8]) {
+        self.0 = crc32c::crc32c_append(self.0, data);
+    }
+
+    fn checksum(&self) -> u32 {
+        self.0
+    }
+}
+
+/// A [`Read`]er computing the checksum of all bytes read through it.
+pub struct ChecksumReader<R, C = Crc32cHasher> {
+    inner: R,
+    checksummer: C,
+}
+
+impl<R, C: Checksummer> ChecksumReader<R, C> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            checksummer: C::default(),
+        }
+    }
+
+    /// The checksum of all bytes read so far.
+    pub fn checksum(&self) -> u32 {
+        self.checksummer.checksum()
+    }
+
+    /// Consume `self`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read, C: Checksummer> Read for ChecksumReader<R, C> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.checksummer.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// A [`Write`]r computing the checksum of all bytes written through it.
+pub struct ChecksumWriter<W, C = Crc32cHasher> {
+    inner: W,
+    checksummer: C,
+}
+
+impl<W, C: Checksummer> ChecksumWriter<W, C> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            checksummer: C::default(),
+        }
+    }
+
+    /// The checksum of all bytes written so far.
+    pub fn checksum(&self) -> u32 {
+        self.checksummer.checksum()
+    }
+
+    /// Consume `self`, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write, C: Checksummer> Write for ChecksumWriter<W, C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.checksummer.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Re-frame the commits read from `src` with a different checksum algorithm,
+/// writing them to `dst`.
+///
+/// Each commit is verified using the [`Checksummer`] `F`, and written with the
+/// checksum computed by `T`. The commit header and records are written exactly
+/// as read. Reading stops at EOF, as determined by [`Header::decode`].
+///
+/// This is intended for migrating segments to a new checksum algorithm.
+/// Note that `src` and `dst` are expected to be positioned after the segment
+/// header, which the caller must update to reflect [`Checksummer::ALGORITHM`]
+/// of `T`.
+///
+/// If a commit's checksum doesn't match, an error of kind
+/// [`io::ErrorKind::InvalidData`] with an inner error downcastable to
+/// [`ChecksumMismatch`] is returned.
+///
+/// Returns the number of commits written to `dst`.
+pub fn reframe<R: Read, W: Write, F: Checksummer, T: Checksummer>(mut src: R, mut dst: W) -> io::Result<usize> {
+    let mut buf = Vec::new();
+    let mut count = 0;
+
+    while let Some(hdr) = Header::decode(&mut src)? {
+        buf.clear();
+        buf.extend_from_slice(&hdr.min_tx_offset.to_le_bytes());
+        buf.extend_from_slice(&hdr.epoch.to_le_bytes());
+        buf.extend_from_slice(&hdr.n.to_le_bytes());
+        buf.extend_from_slice(&hdr.len.to_le_bytes());
+        buf.resize(Header::LEN + hdr.len as usize, 0);
+        src.read_exact(&mut buf[Header::LEN..])?;
+
+        let stored = decode_u32(&mut src)?;
+        let mut from = F::default();
+        from.update(&buf);
+        if from.checksum() != stored {
+            return Err(invalid_data(ChecksumMismatch));
+        }
+
+        let mut to = T::default();
+        to.update(&buf);
+        dst.write_all(&buf)?;
+        dst.write_all(&to.checksum().to_le_bytes())?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// The outcome of [`verify_segment`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VerifyReport {
+    /// The number of commits with a valid checksum.
+    pub commits_ok: u64,
+    /// The `min_tx_offset` of the first commit with an invalid checksum, if any.
+    pub first_bad_offset: Option<u64>,
+    /// The number of bytes read, including the bad commit, if any.
+    pub bytes_scanned: u64,
+}
+
+/// Verify the checksums of all commits read from `reader`, using constant
+/// memory.
+///
+/// `reader` is expected to be positioned after the segment header. The
+/// records of each commit are streamed through the checksum computation
+/// without buffering them.
+///
+/// Verification stops at EOF or an all-zeroes commit header, as determined by
+/// [`Header::decode`], or at the first commit with an invalid checksum, which
+/// is recorded in [`VerifyReport::first_bad_offset`].
+///
+/// A commit header declaring more than `max_commit_bytes` of records results
+/// in an error of kind [`io::ErrorKind::InvalidData`], as it likely indicates a
+/// corrupted header. Truncated commits result in an error of kind
+/// [`io::ErrorKind::UnexpectedEof`].
+pub fn verify_segment<R: Read>(mut reader: R, max_commit_bytes: u64) -> io::Result<VerifyReport> {
+    let mut report = VerifyReport::default();
+    loop {
+        let mut reader = ChecksumReader::<_, Crc32cHasher>::new(&mut reader);
+        let Some(hdr) = Header::decode(&mut reader)? else {
+            break;
+        };
+        let len = hdr.len as u64;
+        if len > max_commit_bytes {
+            return Err(invalid_data(format!(
+                "commit {} exceeds maximum size: {} > {}",
+                hdr.min_tx_offset, len, max_commit_bytes
+            )));
+        }
+        if io::copy(&mut (&mut reader).take(len), &mut io::sink())? < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        let chk = reader.checksum();
+        let crc = decode_u32(reader.into_inner())?;
+        report.bytes_scanned += Commit::FRAMING_LEN as u64 + len;
+
+        if chk != crc {
+            report.first_bad_offset = Some(hdr.min_tx_offset);
+            break;
+        }
+        report.commits_ok += 1;
+    }
+
+    Ok(report)
+}
+
+/// Statistics about the commits in a segment, as computed by [`describe`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SegmentStats {
+    /// The number of commits with a valid checksum.
+    pub commit_count: u64,
+    /// The number of transactions in the commits with a valid checksum.
+    pub tx_count: u64,
+    /// The number of bytes read, including corrupt commits.
+    pub total_bytes: u64,
+    /// The smallest transaction offset in a commit with a valid checksum.
+    pub min_offset: Option<u64>,
+    /// The largest transaction offset in a commit with a valid checksum.
+    pub max_offset: Option<u64>,
+    /// The number of commits with an invalid checksum.
+    pub corrupt_count: u64,
+}
+
+/// Compute [`SegmentStats`] over the commits read from `reader`, in one pass.
+///
+/// `reader` is expected to be positioned after the segment header. Like
+/// [`verify_segment`], the records of each commit are streamed through the
+/// checksum computation without buffering them.
+///
+/// A commit with an invalid checksum is counted as corrupt, and the scan
+/// resumes after it, trusting the length declared in its header. As the
+/// header itself may be corrupt, the transaction offsets of corrupt commits
+/// are not taken into account.
+///
+/// The scan stops at EOF or an all-zeroes commit header, as determined by
+/// [`Header::decode`]. Truncated commits result in an error of kind
+/// [`io::ErrorKind::UnexpectedEof`].
+pub fn describe<R: Read>(mut reader: R) -> io::Result<SegmentStats> {
+    let mut stats = SegmentStats::default();
+    loop {
+        let mut reader = ChecksumReader::<_, Crc32cHasher>::new(&mut reader);
+        let Some(hdr) = Header::decode(&mut reader)? else {
+            break;
+        };
+        let len = hdr.len as u64;
+        if io::copy(&mut (&mut reader).take(len), &mut io::sink())? < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        let chk = reader.checksum();
+        let crc = decode_u32(reader.into_inner())?;
+        stats.total_bytes += Commit::FRAMING_LEN as u64 + len;
+
+        if chk != crc {
+            stats.corrupt_count += 1;
+            continue;
+        }
+        stats.commit_count += 1;
+        stats.tx_count += hdr.n as u64;
+        if hdr.n > 0 {
+            let max = hdr.min_tx_offset + hdr.n as u64 - 1;
+            stats.min_offset = Some(
+                stats
+                    .min_offset
+                    .map_or(hdr.min_tx_offset, |min| min.min(hdr.min_tx_offset)),
+            );
+            stats.max_offset = Some(stats.max_offset.map_or(max, |prev| prev.max(max)));
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Tally the sizes of the commits read from `reader` into `buckets`.
+///
+/// `reader` is expected to be positioned after the segment header. Only the
+/// commit headers are decoded, the records and checksum of each commit are
+/// skipped over without buffering them. Checksums are not verified.
+///
+/// `buckets` are the inclusive upper bounds of the buckets, in ascending
+/// order. The framed size of a commit, i.e. [`Commit::encoded_len`], is
+/// counted in the first bucket whose bound is greater than or equal to it.
+/// The returned `Vec` has one more element than `buckets`, which counts the
+/// commits larger than the last bound.
+///
+/// The scan stops at EOF or an all-zeroes commit header, as determined by
+/// [`Header::decode`]. Truncated commits result in an error of kind
+/// [`io::ErrorKind::UnexpectedEof`].
+pub fn size_histogram<R: Read>(mut reader: R, buckets: &[u64]) -> io::Result<Vec<u64>> {
+    debug_assert!(buckets.is_sorted(), "buckets must be sorted");
+
+    let mut counts = vec![0; buckets.len() + 1];
+    while let Some(hdr) = Header::decode(&mut reader)? {
+        let skip = hdr.len as u64 + Commit::CHECKSUM_LEN as u64;
+        if io::copy(&mut (&mut reader).take(skip), &mut io::sink())? < skip {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        let size = Header::LEN as u64 + skip;
+        counts[buckets.partition_point(|&bound| bound < size)] += 1;
+    }
+
+    Ok(counts)
+}
+
+/// Compute a digest over the records of all commits read from `reader`.
+///
+/// `reader` is expected to be positioned after the segment header. Each
+/// commit is decoded and its checksum verified, as by [`Commit::decode`], and
+/// its records are fed into a SHA-256 hasher. The commit framing is not part
+/// of the digest, so the digest is independent of how the records are divided
+/// into commits. This allows comparing the logical contents of segments
+/// written by different nodes.
+pub fn segment_digest<R: Read>(mut reader: R) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    while let Some(commit) = Commit::decode(&mut reader)? {
+        hasher.update(&commit.records);
+    }
+
+    Ok(hasher.finalize().into())
+}
+
+/// Decode up to `n` commits from `reader`, e.g. to preview a segment.
+///
+/// `reader` is expected to be positioned after the segment header. Fewer than
+/// `n` commits are returned if EOF or an all-zeroes commit header is reached
+/// first. Checksums are verified as by [`Commit::decode`].
+///
+/// Nothing is read from `reader` after the `n`th commit, so a corrupt or
+/// truncated tail of the segment doesn't cause an error.
+pub fn decode_head<R: Read>(reader: R, n: usize) -> io::Result<Vec<Commit>> {
+    CommitReader::new(reader).take(n).collect()
+}
+
 /// Numbers needed to compute [`crate::segment::Header`].
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Metadata {
@@ -357,6 +1083,33 @@ impl Metadata {
     pub fn extract<R: io::Read>(reader: R) -> io::Result<Option<Self>> {
         Commit::decode(reader).map(|maybe_commit| maybe_commit.map(Self::from))
     }
+
+    /// Fold the [`Metadata`] of a sequence of consecutive commits into a
+    /// single [`Metadata`] spanning all of them.
+    ///
+    /// The `tx_range` of the result spans from the start of the first to the
+    /// end of the last commit, `size_in_bytes` is the sum of all sizes, and
+    /// `epoch` is the maximum epoch.
+    ///
+    /// Returns `None` if `iter` is empty.
+    ///
+    /// # Panics
+    ///
+    /// If the `tx_range`s of the commits are not contiguous.
+    pub fn combine(iter: impl Iterator<Item = Metadata>) -> Option<Metadata> {
+        iter.reduce(|acc, next| {
+            assert_eq!(
+                acc.tx_range.end, next.tx_range.start,
+                "non-contiguous commit metadata: {:?} followed by {:?}",
+                acc.tx_range, next.tx_range
+            );
+            Metadata {
+                tx_range: acc.tx_range.start..next.tx_range.end,
+                size_in_bytes: acc.size_in_bytes + next.size_in_bytes,
+                epoch: acc.epoch.max(next.epoch),
+            }
+        })
+    }
 }
 
 impl From<Commit> for Metadata {
@@ -375,6 +1128,68 @@ fn decode_u32<R: Read>(mut read: R) -> io::Result<u32> {
     Ok(u32::from_le_bytes(buf))
 }
 
+/// Whether `value` is a valid value of `ty`, see [`Commit::validate_schema`].
+fn product_has_type(value: &ProductValue, ty: &ProductType) -> bool {
+    value.elements.len() == ty.elements.len()
+        && value
+            .elements
+            .iter()
+            .zip(&*ty.elements)
+            .all(|(value, elem)| value_has_type(value, &elem.algebraic_type))
+}
+
+/// Whether `value` is a valid value of `ty`.
+///
+/// Type references can't be resolved, so a value is never valid for them.
+fn value_has_type(value: &AlgebraicValue, ty: &AlgebraicType) -> bool {
+    match (value, ty) {
+        (AlgebraicValue::Product(value), AlgebraicType::Product(ty)) => product_has_type(value, ty),
+        (AlgebraicValue::Sum(value), AlgebraicType::Sum(ty)) => sum_has_type(value, ty),
+        (AlgebraicValue::Array(value), AlgebraicType::Array(ty)) => array_has_type(value, &ty.elem_ty),
+        (AlgebraicValue::Product(_) | AlgebraicValue::Sum(_) | AlgebraicValue::Array(_), _) => false,
+        // Scalars always have a type.
+        (value, ty) => value.type_of().as_ref() == Some(ty),
+    }
+}
+
+/// Whether `value` is a valid value of `ty`.
+fn sum_has_type(value: &SumValue, ty: &SumType) -> bool {
+    ty.variants
+        .get(value.tag as usize)
+        .is_some_and(|variant| value_has_type(&value.value, &variant.algebraic_type))
+}
+
+/// Whether all elements of `value` are valid values of `elem_ty`.
+fn array_has_type(value: &ArrayValue, elem_ty: &AlgebraicType) -> bool {
+    use AlgebraicType as T;
+    use ArrayValue as V;
+
+    match (value, elem_ty) {
+        // An empty array is valid for any element type.
+        _ if value.is_empty() => true,
+        (V::Product(values), T::Product(ty)) => values.iter().all(|v| product_has_type(v, ty)),
+        (V::Sum(values), T::Sum(ty)) => values.iter().all(|v| sum_has_type(v, ty)),
+        (V::Array(values), T::Array(ty)) => values.iter().all(|v| array_has_type(v, &ty.elem_ty)),
+        (V::Bool(_), T::Bool)
+        | (V::I8(_), T::I8)
+        | (V::U8(_), T::U8)
+        | (V::I16(_), T::I16)
+        | (V::U16(_), T::U16)
+        | (V::I32(_), T::I32)
+        | (V::U32(_), T::U32)
+        | (V::I64(_), T::I64)
+        | (V::U64(_), T::U64)
+        | (V::I128(_), T::I128)
+        | (V::U128(_), T::U128)
+        | (V::I256(_), T::I256)
+        | (V::U256(_), T::U256)
+        | (V::F32(_), T::F32)
+        | (V::F64(_), T::F64)
+        | (V::String(_), T::String) => true,
+        _ => false,
+    }
+}
+
 fn decode_error(e: DecodeError) -> io::Error {
     invalid_data(e)
 }
@@ -412,6 +1227,51 @@ mod tests {
         assert_eq!(commit, commit2);
     }
 
+    #[test]
+    fn all_zeroes_header_is_padding() {
+        assert!(Header::decode(&[0; Header::LEN][..]).unwrap().is_none());
+        // The v0 header is shorter, and so is its padding.
+        assert!(Header::decode_internal(&[0; Header::LEN - 8][..], Version::V0)
+            .unwrap()
+            .is_none());
+
+        // Only a header of all zeroes is padding.
+        let mut hdr = [0; Header::LEN];
+        hdr[Header::LEN - 1] = 1;
+        assert!(Header::decode(&hdr[..]).unwrap().is_some());
+    }
+
+    #[test]
+    fn records_multiset_eq_ignores_order() {
+        let commit = |min_tx_offset, records: &[[u8; 4]]| Commit {
+            min_tx_offset,
+            epoch: Commit::DEFAULT_EPOCH,
+            n: records.len() as u16,
+            records: records.concat(),
+        };
+        let de = ArrayDecoder::<4>;
+        let v = DEFAULT_LOG_FORMAT_VERSION;
+
+        let a = commit(0, &[[1; 4], [2; 4], [2; 4], [3; 4]]);
+        let b = commit(10, &[[2; 4], [3; 4], [1; 4], [2; 4]]);
+        assert_ne!(a.records, b.records);
+        assert!(a.records_multiset_eq(&b, v, &de).unwrap());
+        assert!(b.records_multiset_eq(&a, v, &de).unwrap());
+
+        // Multiplicities matter.
+        let c = commit(0, &[[1; 4], [2; 4], [3; 4], [3; 4]]);
+        assert!(!a.records_multiset_eq(&c, v, &de).unwrap());
+        assert!(!a
+            .records_multiset_eq(&commit(0, &[[1; 4], [2; 4], [3; 4]]), v, &de)
+            .unwrap());
+
+        let truncated = Commit {
+            records: vec![1; 6],
+            ..a.clone()
+        };
+        assert!(a.records_multiset_eq(&truncated, v, &de).is_err());
+    }
+
     #[test]
     fn into_transactions_can_skip_txs() {
         enable_logging();
@@ -443,7 +1303,714 @@ mod tests {
         )
     }
 
+    #[test]
+    fn metadata_combine_contiguous() {
+        let (commits, buf) = write_commits(3);
+        let combined = Metadata::combine(commits.into_iter().map(Metadata::from)).unwrap();
+        assert_eq!(
+            combined,
+            Metadata {
+                tx_range: 0..6,
+                size_in_bytes: buf.len() as u64,
+                epoch: Commit::DEFAULT_EPOCH,
+            }
+        );
+    }
+
+    #[test]
+    fn metadata_combine_single_and_empty() {
+        let (commits, _) = write_commits(1);
+        let meta = Metadata::from(commits[0].clone());
+        assert_eq!(Metadata::combine([meta.clone()].into_iter()), Some(meta));
+        assert_eq!(Metadata::combine(std::iter::empty()), None);
+    }
+
+    #[test]
+    #[should_panic = "non-contiguous"]
+    fn metadata_combine_rejects_gaps() {
+        let (commits, _) = write_commits(3);
+        Metadata::combine([&commits[0], &commits[2]].into_iter().cloned().map(Metadata::from));
+    }
+
+    #[test]
+    fn verify_segment_reports_first_bad_commit() {
+        let (commits, mut buf) = write_commits(3);
+
+        let report = verify_segment(buf.as_slice(), 64).unwrap();
+        assert_eq!(
+            report,
+            VerifyReport {
+                commits_ok: 3,
+                first_bad_offset: None,
+                bytes_scanned: buf.len() as u64,
+            }
+        );
+
+        let err = verify_segment(buf.as_slice(), 63).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Corrupt the records of the second commit.
+        buf[commits[0].encoded_len() + Header::LEN] ^= 0xff;
+        let report = verify_segment(buf.as_slice(), 64).unwrap();
+        assert_eq!(
+            report,
+            VerifyReport {
+                commits_ok: 1,
+                first_bad_offset: Some(commits[1].min_tx_offset),
+                bytes_scanned: (commits[0].encoded_len() + commits[1].encoded_len()) as u64,
+            }
+        );
+    }
+
+    #[test]
+    fn verify_segment_stops_at_preallocated_space() {
+        let (_, mut buf) = write_commits(3);
+        let len = buf.len() as u64;
+        buf.resize(buf.len() + 4096, 0);
+
+        let report = verify_segment(buf.as_slice(), 64).unwrap();
+        assert_eq!(
+            report,
+            VerifyReport {
+                commits_ok: 3,
+                first_bad_offset: None,
+                bytes_scanned: len,
+            }
+        );
+    }
+
+    #[test]
+    fn describe_counts_good_and_corrupt_commits() {
+        let (commits, mut buf) = write_commits(4);
+
+        // Corrupt the records of the last commit, and pad with zeroes.
+        let len = buf.len();
+        buf[len - commits[3].encoded_len() + Header::LEN] ^= 0xff;
+        buf.extend_from_slice(&[0; Header::LEN]);
+
+        let stats = describe(buf.as_slice()).unwrap();
+        assert_eq!(
+            stats,
+            SegmentStats {
+                commit_count: 3,
+                tx_count: 6,
+                total_bytes: len as u64,
+                min_offset: Some(0),
+                max_offset: Some(5),
+                corrupt_count: 1,
+            }
+        );
+
+        // The scan resumes after a corrupt commit.
+        buf[Header::LEN] ^= 0xff;
+        let stats = describe(buf.as_slice()).unwrap();
+        assert_eq!((stats.commit_count, stats.corrupt_count), (2, 2));
+        assert_eq!((stats.min_offset, stats.max_offset), (Some(2), Some(5)));
+
+        assert_eq!(describe(&[][..]).unwrap(), SegmentStats::default());
+    }
+
+    #[test]
+    fn size_histogram_tallies_framed_sizes() {
+        let commits = [8, 100, 8, 1000, 40]
+            .into_iter()
+            .enumerate()
+            .map(|(i, len)| Commit {
+                min_tx_offset: i as u64,
+                epoch: Commit::DEFAULT_EPOCH,
+                n: 1,
+                records: vec![1; len],
+            })
+            .collect::<Vec<_>>();
+        let mut buf = Vec::new();
+        for commit in &commits {
+            commit.write(&mut buf).unwrap();
+        }
+        // Zero padding terminates the scan.
+        buf.extend_from_slice(&[0; 64]);
+
+        // Framed sizes: 34, 126, 34, 1026, 66
+        let counts = size_histogram(buf.as_slice(), &[34, 64, 128]).unwrap();
+        assert_eq!(counts, [2, 0, 2, 1]);
+
+        let err = size_histogram(&buf[..commits[0].encoded_len() - 1], &[64]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn decode_exact_rejects_trailing_bytes() {
+        let (commits, mut buf) = write_commits(1);
+
+        assert_eq!(Commit::decode_exact(&buf).unwrap(), commits[0]);
+
+        buf.push(0);
+        let err = Commit::decode_exact(&buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = Commit::decode_exact(&[]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    /// A test-only checksummer which always yields the same checksum.
+    #[derive(Default)]
+    struct Fixed;
+
+    impl Checksummer for Fixed {
+        const ALGORITHM: u8 = CHECKSUM_ALGORITHM_CRC32C;
+
+        fn update(&mut self, _data: &[u8]) {}
+
+        fn checksum(&self) -> u32 {
+            0xdead_beef
+        }
+    }
+
+    #[test]
+    fn empty_input_decodes_to_none() {
+        let empty: &[u8] = &[];
+
+        assert!(Header::decode(empty).unwrap().is_none());
+        for v in [Version::V0, Version::V1] {
+            assert!(Header::decode_internal(empty, v).unwrap().is_none());
+        }
+        assert!(Commit::decode(empty).unwrap().is_none());
+        assert!(Commit::decode_with_crc(empty).unwrap().is_none());
+        assert!(Commit::decode_aligned(empty, 8).unwrap().is_none());
+        assert!(StoredCommit::decode(empty).unwrap().is_none());
+        assert!(StoredCommit::decode_with::<_, Crc32cHasher>(empty).unwrap().is_none());
+        assert!(Metadata::extract(empty).unwrap().is_none());
+        assert!(CommitReader::new(empty).next_commit().unwrap().is_none());
+        assert_eq!(size_histogram(empty, &[10]).unwrap(), [0, 0]);
+        assert_eq!(segment_digest(empty).unwrap(), <[u8; 32]>::from(Sha256::digest([])));
+        // Not optional, so an empty buffer is an error.
+        assert_eq!(
+            Commit::decode_exact(empty).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn split_at_record_boundary() {
+        let records: [[u8; 4]; 3] = [[1; 4], [2; 4], [3; 4]];
+        let commit = Commit {
+            min_tx_offset: 10,
+            epoch: 1,
+            n: 3,
+            records: records.concat(),
+        };
+        let de = ArrayDecoder::<4>;
+        let decode = |commit: Commit| {
+            commit
+                .into_transactions(DEFAULT_LOG_FORMAT_VERSION, 0, &de)
+                .map(|tx| tx.map(|tx| (tx.offset, tx.txdata)))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+
+        let (head, tail) = commit
+            .clone()
+            .split_at_record(1, DEFAULT_LOG_FORMAT_VERSION, &de)
+            .unwrap();
+        assert_eq!((head.min_tx_offset, head.n, head.epoch), (10, 1, 1));
+        assert_eq!((tail.min_tx_offset, tail.n, tail.epoch), (11, 2, 1));
+        assert_eq!(decode(head), [(10, records[0])]);
+        assert_eq!(decode(tail), [(11, records[1]), (12, records[2])]);
+
+        assert!(commit.split_at_record(4, DEFAULT_LOG_FORMAT_VERSION, &de).is_err());
+    }
+
+    #[test]
+    fn validate_schema_reports_mismatch() {
+        use spacetimedb_sats::{bsatn, product};
+
+        /// Decodes rows of the given type, i.e. the type they were written with.
+        struct RowDecoder(ProductType);
+
+        impl Decoder for RowDecoder {
+            type Record = ProductValue;
+            type Error = DecodeError;
+
+            fn decode_record<'a, R: BufReader<'a>>(
+                &self,
+                _version: u8,
+                _tx_offset: u64,
+                reader: &mut R,
+            ) -> Result<Self::Record, Self::Error> {
+                bsatn::decode(&self.0, reader)
+            }
+
+            fn skip_record<'a, R: BufReader<'a>>(
+                &self,
+                version: u8,
+                tx_offset: u64,
+                reader: &mut R,
+            ) -> Result<(), Self::Error> {
+                self.decode_record(version, tx_offset, reader).map(drop)
+            }
+        }
+
+        let commit = |rows: &[ProductValue]| Commit {
+            min_tx_offset: 10,
+            epoch: Commit::DEFAULT_EPOCH,
+            n: rows.len() as u16,
+            records: rows.iter().flat_map(|row| bsatn::to_vec(row).unwrap()).collect(),
+        };
+        let expected = ProductType::from([("id", AlgebraicType::U32), ("name", AlgebraicType::String)]);
+
+        let matching = commit(&[product![1u32, "a"], product![2u32, "b"]]);
+        let de = RowDecoder(expected.clone());
+        matching
+            .validate_schema(DEFAULT_LOG_FORMAT_VERSION, &de, &expected)
+            .unwrap();
+
+        // Written with a wider `id` column.
+        let written = ProductType::from([("id", AlgebraicType::U64), ("name", AlgebraicType::String)]);
+        let mismatching = commit(&[product![1u64, "a"], product![2u64, "b"]]);
+        let de = RowDecoder(written);
+        let err = mismatching
+            .validate_schema(DEFAULT_LOG_FORMAT_VERSION, &de, &expected)
+            .unwrap_err();
+        assert_eq!(err.tx_offset, 10);
+        assert_eq!(err.expected, expected);
+        assert_eq!(
+            err.actual,
+            Some(AlgebraicType::product([AlgebraicType::U64, AlgebraicType::String]))
+        );
+
+        // Records which can't be decoded at all, as they are too short.
+        let de = RowDecoder(ProductType::from([AlgebraicType::U256, AlgebraicType::U256]));
+        let err = mismatching
+            .validate_schema(DEFAULT_LOG_FORMAT_VERSION, &de, &expected)
+            .unwrap_err();
+        assert_eq!((err.tx_offset, err.actual), (10, None));
+        assert!(matches!(err.source, Some(DecodeError::BufferLength { .. })));
+
+        // Sums and arrays are checked element-wise.
+        let tagged = ProductType::from([
+            AlgebraicType::option(AlgebraicType::U32),
+            AlgebraicType::array(AlgebraicType::String),
+        ]);
+        let rows = [
+            product![
+                AlgebraicValue::OptionSome(AlgebraicValue::U32(1)),
+                AlgebraicValue::Array(ArrayValue::String([].into()))
+            ],
+            product![
+                AlgebraicValue::OptionNone(),
+                AlgebraicValue::Array(ArrayValue::String(["a".into()].into()))
+            ],
+        ];
+        let de = RowDecoder(tagged.clone());
+        commit(&rows)
+            .validate_schema(DEFAULT_LOG_FORMAT_VERSION, &de, &tagged)
+            .unwrap();
+        let numbers = ProductType::from([
+            AlgebraicType::option(AlgebraicType::U32),
+            AlgebraicType::array(AlgebraicType::U32),
+        ]);
+        let err = commit(&rows)
+            .validate_schema(DEFAULT_LOG_FORMAT_VERSION, &de, &numbers)
+            .unwrap_err();
+        assert_eq!(err.tx_offset, 11);
+        assert!(err.source.is_none());
+    }
+
+    #[test]
+    fn segment_digest_ignores_framing() {
+        let encode = |chunks: &[&[u8]]| {
+            let mut buf = Vec::new();
+            let mut offset = 0;
+            for records in chunks {
+                Commit {
+                    min_tx_offset: offset,
+                    epoch: Commit::DEFAULT_EPOCH,
+                    n: records.len() as u16,
+                    records: records.to_vec(),
+                }
+                .write(&mut buf)
+                .unwrap();
+                offset += records.len() as u64;
+            }
+            buf
+        };
+
+        let one = encode(&[b"abcdef"]);
+        let three = encode(&[b"ab", b"cd", b"ef"]);
+        assert_ne!(one, three);
+        assert_eq!(segment_digest(&one[..]).unwrap(), segment_digest(&three[..]).unwrap());
+
+        let changed = encode(&[b"ab", b"cX", b"ef"]);
+        assert_ne!(
+            segment_digest(&three[..]).unwrap(),
+            segment_digest(&changed[..]).unwrap()
+        );
+
+        let mut corrupt = three.clone();
+        corrupt[Header::LEN] ^= 1;
+        assert_eq!(
+            segment_digest(&corrupt[..]).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn reencode_framing_keeps_records() {
+        let commit = Commit {
+            min_tx_offset: 10,
+            epoch: Commit::DEFAULT_EPOCH,
+            n: 3,
+            records: (0..42).collect(),
+        };
+
+        let framed = commit.reencode_framing(100, 2);
+        assert_eq!(framed.len(), commit.encoded_len());
+        let decoded = Commit::decode_exact(&framed).unwrap();
+        assert_eq!((decoded.min_tx_offset, decoded.epoch, decoded.n), (100, 2, 3));
+        assert_eq!(decoded.records_as_bsatn(), commit.records_as_bsatn());
+
+        // Re-encoding with the original offset is the same as writing.
+        let mut buf = Vec::new();
+        commit.write(&mut buf).unwrap();
+        assert_eq!(commit.reencode_framing(10, Commit::DEFAULT_EPOCH), buf);
+    }
+
+    #[test]
+    fn content_hash_is_stable() {
+        let commit = Commit {
+            min_tx_offset: 10,
+            epoch: Commit::DEFAULT_EPOCH,
+            n: 3,
+            records: (0..42).collect(),
+        };
+        let hash = commit.content_hash();
+
+        let mut buf = Vec::new();
+        commit.write(&mut buf).unwrap();
+        assert_eq!(Commit::decode_exact(&buf).unwrap().content_hash(), hash);
+        // The epoch is part of the framing, not the content.
+        let reframed = Commit::decode_exact(&commit.reencode_framing(10, 7)).unwrap();
+        assert_eq!(reframed.content_hash(), hash);
+
+        let mut changed = commit.clone();
+        changed.records[41] ^= 1;
+        assert_ne!(changed.content_hash(), hash);
+        let moved = Commit {
+            min_tx_offset: 11,
+            ..commit
+        };
+        assert_ne!(moved.content_hash(), hash);
+    }
+
+    #[test]
+    fn framing_constants_match_written_layout() {
+        let commit = Commit {
+            min_tx_offset: 0x0102_0304_0506_0708,
+            epoch: 0x1112_1314_1516_1718,
+            n: 0x2122,
+            records: vec![0xaa; 13],
+        };
+        let mut buf = Vec::new();
+        let crc = commit.write(&mut buf).unwrap();
+
+        let offsets = Header::field_offsets();
+        assert_eq!(buf[offsets.min_tx_offset], commit.min_tx_offset.to_le_bytes());
+        assert_eq!(buf[offsets.epoch], commit.epoch.to_le_bytes());
+        assert_eq!(buf[offsets.n], commit.n.to_le_bytes());
+        assert_eq!(buf[offsets.len], 13u32.to_le_bytes());
+        assert_eq!(&buf[Header::LEN..Header::LEN + 13], &commit.records[..]);
+
+        let checksum_offset = Commit::checksum_offset(commit.records.len());
+        assert_eq!(buf[checksum_offset..], crc.to_le_bytes());
+        assert_eq!(buf.len(), checksum_offset + Commit::CHECKSUM_LEN);
+        assert_eq!(buf.len(), Commit::FRAMING_LEN + commit.records.len());
+        assert_eq!(crc32c::crc32c(&buf[..checksum_offset]), crc);
+
+        let header = Header::decode(&buf[..]).unwrap().unwrap();
+        assert_eq!(
+            header,
+            Header {
+                min_tx_offset: commit.min_tx_offset,
+                epoch: commit.epoch,
+                n: commit.n,
+                len: 13,
+            }
+        );
+    }
+
+    #[test]
+    fn try_write_checks_capacity() {
+        let commit = Commit {
+            min_tx_offset: 0,
+            epoch: Commit::DEFAULT_EPOCH,
+            n: 1,
+            records: vec![1; 32],
+        };
+        let needed = commit.encoded_len();
+
+        let mut buf = vec![0; needed - 1];
+        let err = commit.try_write(&mut buf[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::StorageFull);
+        let err = err.into_inner().unwrap().downcast::<InsufficientSpace>().unwrap();
+        assert_eq!((err.needed, err.available), (needed as u64, needed as u64 - 1));
+        assert!(buf.iter().all(|&b| b == 0), "commit was partially written");
+
+        let mut buf = vec![0; needed + 1];
+        let mut out = &mut buf[..];
+        let crc = commit.try_write(&mut out).unwrap();
+        assert_eq!(out.remaining_capacity(), 1);
+        let (decoded, decoded_crc) = Commit::decode_with_crc(&buf[..]).unwrap().unwrap();
+        assert_eq!((decoded, decoded_crc), (commit, crc));
+    }
+
+    #[test]
+    fn pluggable_checksummer() {
+        let (commits, _) = write_commits(1);
+        let commit = &commits[0];
+
+        let mut buf = Vec::new();
+        assert_eq!(commit.write_with::<_, Fixed>(&mut buf).unwrap(), 0xdead_beef);
+        assert!(buf.ends_with(&0xdead_beef_u32.to_le_bytes()));
+
+        let stored = StoredCommit::decode_with::<_, Fixed>(buf.as_slice()).unwrap().unwrap();
+        assert_eq!(stored.checksum, 0xdead_beef);
+        assert_eq!(&Commit::from(stored), commit);
+
+        // The default checksummer disagrees, and vice versa.
+        let err = Commit::decode(buf.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let mut crc = Vec::new();
+        commit.write(&mut crc).unwrap();
+        assert!(StoredCommit::decode_with::<_, Fixed>(crc.as_slice()).is_err());
+    }
+
+    #[test]
+    fn decode_with_crc_returns_checksum() {
+        let (commits, buf) = write_commits(2);
+
+        let mut reader = buf.as_slice();
+        for commit in &commits {
+            let framed = &reader[..commit.encoded_len()];
+            let (decoded, crc) = Commit::decode_with_crc(&mut reader).unwrap().unwrap();
+            assert_eq!(commit, &decoded);
+            assert_eq!(crc, crc32c::crc32c(&framed[..framed.len() - Commit::CHECKSUM_LEN]));
+        }
+        assert!(Commit::decode_with_crc(reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn into_transactions_owned_outlives_decoder() {
+        let commit = Commit {
+            min_tx_offset: 5,
+            n: 3,
+            records: (0..3).flat_map(|i| [i; 32]).collect(),
+            epoch: Commit::DEFAULT_EPOCH,
+        };
+
+        let txs = {
+            let de = ArrayDecoder::<32>;
+            commit.into_transactions_owned(DEFAULT_LOG_FORMAT_VERSION, 6, &de)
+        };
+
+        let txs = txs.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(
+            txs,
+            vec![
+                Transaction {
+                    offset: 6,
+                    txdata: [1; 32]
+                },
+                Transaction {
+                    offset: 7,
+                    txdata: [2; 32]
+                }
+            ]
+        );
+    }
+
+    fn write_commits(n: u64) -> (Vec<Commit>, Vec<u8>) {
+        let commits = (0..n)
+            .map(|i| Commit {
+                min_tx_offset: i * 2,
+                n: 2,
+                records: vec![i as u8; 64],
+                epoch: Commit::DEFAULT_EPOCH,
+            })
+            .collect::<Vec<_>>();
+        let mut buf = Vec::new();
+        for commit in &commits {
+            commit.write(&mut buf).unwrap();
+        }
+
+        (commits, buf)
+    }
+
+    #[test]
+    fn decode_head_leaves_tail_untouched() {
+        let (commits, mut buf) = write_commits(5);
+        // Corrupt the third commit, and truncate the last.
+        buf[2 * commits[0].encoded_len() + Header::LEN] ^= 0xff;
+        buf.truncate(buf.len() - 1);
+
+        let mut reader = buf.as_slice();
+        assert_eq!(decode_head(&mut reader, 2).unwrap(), &commits[..2]);
+        assert_eq!(reader.len(), buf.len() - 2 * commits[0].encoded_len());
+
+        assert_eq!(decode_head(buf.as_slice(), 0).unwrap(), []);
+        assert!(decode_head(buf.as_slice(), 3).is_err());
+        let (commits, buf) = write_commits(2);
+        assert_eq!(decode_head(buf.as_slice(), 5).unwrap(), commits);
+    }
+
+    #[test]
+    fn commit_reader_reads_to_eof() {
+        let (commits, buf) = write_commits(5);
+
+        let mut reader = CommitReader::new(buf.as_slice());
+        let read = reader.by_ref().collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(commits, read);
+        assert!(reader.next_commit().unwrap().is_none());
+    }
+
+    #[test]
+    fn commit_reader_stops_at_truncated_tail() {
+        let (commits, mut buf) = write_commits(3);
+        buf.truncate(buf.len() - 8);
+
+        let mut reader = CommitReader::new(buf.as_slice());
+        assert_eq!(commits[0], reader.next_commit().unwrap().unwrap());
+        assert_eq!(commits[1], reader.next_commit().unwrap().unwrap());
+        let err = reader.next_commit().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(reader.next().is_none());
+    }
+
+    /// A toy checksum algorithm for testing [`reframe`].
+    #[derive(Default)]
+    struct Xor32 {
+        acc: [u8; 4],
+        pos: usize,
+    }
+
+    impl Checksummer for Xor32 {
+        const ALGORITHM: u8 = 42;
+
+        fn update(&mut self, data: &[u8]) {
+            for b in data {
+                self.acc[self.pos % 4] ^= b;
+                self.pos += 1;
+            }
+        }
+
+        fn checksum(&self) -> u32 {
+            u32::from_le_bytes(self.acc) ^ 0xa5a5_a5a5
+        }
+    }
+
+    #[test]
+    fn reframe_roundtrip() {
+        let (commits, crc) = write_commits(3);
+
+        let mut xor = Vec::new();
+        assert_eq!(
+            3,
+            reframe::<_, _, Crc32cHasher, Xor32>(crc.as_slice(), &mut xor).unwrap()
+        );
+        assert_eq!(crc.len(), xor.len());
+        assert_ne!(crc, xor);
+        // Decoding with the wrong algorithm fails.
+        let err = reframe::<_, _, Crc32cHasher, Crc32cHasher>(xor.as_slice(), io::sink()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut back = Vec::new();
+        assert_eq!(
+            3,
+            reframe::<_, _, Xor32, Crc32cHasher>(xor.as_slice(), &mut back).unwrap()
+        );
+        assert_eq!(crc, back);
+        let decoded = CommitReader::new(back.as_slice())
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(commits, decoded);
+    }
+
+    #[test]
+    fn decode_aligned_skips_padding() {
+        const ALIGN: usize = 8;
+
+        let commits = (0..3)
+            .map(|i| Commit {
+                min_tx_offset: i,
+                n: 1,
+                records: vec![i as u8; 5 + i as usize],
+                epoch: Commit::DEFAULT_EPOCH,
+            })
+            .collect::<Vec<_>>();
+        let mut buf = Vec::new();
+        for commit in &commits {
+            commit.write(&mut buf).unwrap();
+            buf.resize(buf.len().next_multiple_of(ALIGN), 0);
+        }
+
+        let mut reader = buf.as_slice();
+        for commit in &commits {
+            assert_eq!(commit, &Commit::decode_aligned(&mut reader, ALIGN).unwrap().unwrap());
+        }
+        assert!(Commit::decode_aligned(&mut reader, ALIGN).unwrap().is_none());
+
+        // Non-zero padding is rejected.
+        let pos = commits[0].encoded_len();
+        assert_ne!(pos % ALIGN, 0);
+        buf[pos] = 0xff;
+        let err = Commit::decode_aligned(buf.as_slice(), ALIGN).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    fn arb_commit() -> impl Strategy<Value = Commit> {
+        (
+            0..u64::MAX / 2,
+            any::<u16>(),
+            any::<u64>(),
+            proptest::collection::vec(any::<u8>(), 0..1024),
+        )
+            .prop_map(|(min_tx_offset, n, epoch, records)| Commit {
+                min_tx_offset,
+                n,
+                records,
+                epoch,
+            })
+    }
+
     proptest! {
+        #[test]
+        fn encode_decode_roundtrip(commit in arb_commit()) {
+            let mut buf = Vec::new();
+            commit.write(&mut buf).unwrap();
+
+            prop_assert_eq!(buf.len(), commit.encoded_len());
+            prop_assert_eq!(Commit::decode(buf.as_slice()).unwrap(), Some(commit));
+        }
+
+        #[test]
+        fn corruption_is_detected(
+            commit in arb_commit(),
+            pos in any::<prop::sample::Index>(),
+            mask in any::<NonZeroU8>(),
+        ) {
+            let mut buf = Vec::new();
+            commit.write(&mut buf).unwrap();
+
+            let pos = pos.index(buf.len());
+            // Corrupting the `len` field causes the commit to be read with a
+            // different length, which manifests as an I/O error rather than a
+            // checksum mismatch.
+            prop_assume!(!Header::LEN_FIELD.contains(&pos));
+            buf[pos] ^= mask.get();
+
+            let err = Commit::decode(buf.as_slice()).expect_err("corrupted commit should not decode");
+            prop_assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            prop_assert!(err.into_inner().unwrap().downcast::<ChecksumMismatch>().is_ok());
+        }
+
         #[test]
         fn bitflip(pos in Header::LEN..512, mask in any::<NonZeroU8>()) {
             let commit = Commit {
@@ -1,6 +1,6 @@
 use std::io;
 
-use spacetimedb_sats::buffer::DecodeError;
+use spacetimedb_sats::{buffer::DecodeError, AlgebraicType, ProductType};
 use thiserror::Error;
 
 use crate::segment;
@@ -54,6 +54,26 @@ pub struct Append<T> {
     pub source: io::Error,
 }
 
+/// A record doesn't match the expected row type.
+///
+/// Returned by [`crate::Commit::validate_schema`], where `E` is the error
+/// type of the [`crate::Decoder`].
+#[derive(Debug, Error)]
+#[error("schema mismatch at offset={tx_offset}: expected={expected:?} actual={actual:?}")]
+pub struct SchemaMismatch<E> {
+    /// The transaction offset of the mismatching record.
+    pub tx_offset: u64,
+    /// The expected row type.
+    pub expected: ProductType,
+    /// The type of the record, if it could be decoded and its type inferred.
+    ///
+    /// The type of a value can't always be inferred, e.g. for sum values.
+    pub actual: Option<AlgebraicType>,
+    /// The error decoding the record, if it could not be decoded.
+    #[source]
+    pub source: Option<E>,
+}
+
 /// A checksum mismatch was detected.
 ///
 /// Usually wrapped in another error, such as [`io::Error`].
@@ -61,6 +81,19 @@ pub struct Append<T> {
 #[error("checksum mismatch")]
 pub struct ChecksumMismatch;
 
+/// A writer doesn't have enough space left to hold a commit.
+///
+/// Returned by [`crate::Commit::try_write`], wrapped in an [`io::Error`] of
+/// kind [`io::ErrorKind::StorageFull`].
+#[derive(Debug, Error)]
+#[error("insufficient space: needed={needed} available={available}")]
+pub struct InsufficientSpace {
+    /// The encoded length of the commit.
+    pub needed: u64,
+    /// The remaining capacity of the writer.
+    pub available: u64,
+}
+
 #[derive(Debug, Error)]
 pub enum SegmentMetadata {
     #[error("invalid commit encountered")]
@@ -149,6 +149,18 @@ mod async_impls {
         }
     }
 
+    impl crate::stream::ReadAt for Segment {
+        fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+            let inner = self.buf.read().unwrap();
+            let start = (offset as usize).min(inner.len());
+            io::Read::read(&mut &inner[start..], buf)
+        }
+
+        fn size(&self) -> io::Result<u64> {
+            Ok(self.len() as u64)
+        }
+    }
+
     impl AsyncSeek for Segment {
         fn start_seek(self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
             let this = self.get_mut();
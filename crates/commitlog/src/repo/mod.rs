@@ -6,7 +6,7 @@ use crate::{
     commit::Commit,
     error,
     index::{IndexFile, IndexFileMut},
-    segment::{FileLike, Header, Metadata, OffsetIndexWriter, Reader, Writer},
+    segment::{FileLike, Header, Metadata, OffsetIndexWriter, Reader, SegmentChecksum, Writer},
     Options,
 };
 
@@ -155,6 +155,8 @@ pub fn create_segment_writer<R: Repo>(
         max_records_in_commit: opts.max_records_in_commit,
 
         offset_index_head: create_offset_index_writer(repo, offset, opts),
+
+        segment_checksum: SegmentChecksum::default(),
     })
 }
 
@@ -184,6 +186,7 @@ pub fn resume_segment_writer<R: Repo>(
         tx_range,
         size_in_bytes,
         max_epoch,
+        checksum,
     } = match Metadata::extract(offset, &mut storage) {
         Err(error::SegmentMetadata::InvalidCommit { sofar, source }) => {
             warn!("invalid commit in segment {offset}: {source}");
@@ -222,6 +225,8 @@ pub fn resume_segment_writer<R: Repo>(
         max_records_in_commit: opts.max_records_in_commit,
 
         offset_index_head: create_offset_index_writer(repo, offset, opts),
+
+        segment_checksum: checksum,
     }))
 }
 
@@ -92,6 +92,28 @@ pub struct Committed {
     pub checksum: u32,
 }
 
+/// A running checksum over the crc32 checksums of all commits in a segment,
+/// in the order they were written.
+///
+/// Allows to check the integrity of a whole segment against a single value,
+/// e.g. one recorded in a sidecar file, in addition to the per-commit
+/// checksums. The accumulation is order-sensitive, so reordered commits
+/// produce a different value.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SegmentChecksum(u32);
+
+impl SegmentChecksum {
+    /// Accumulate the checksum of the next commit in the segment.
+    pub fn update(&mut self, commit_checksum: u32) {
+        self.0 = crc32c::crc32c_append(self.0, &commit_checksum.to_le_bytes());
+    }
+
+    /// The checksum accumulated so far.
+    pub fn get(&self) -> u32 {
+        self.0
+    }
+}
+
 #[derive(Debug)]
 pub struct Writer<W: io::Write> {
     pub(crate) commit: Commit,
@@ -103,6 +125,8 @@ pub struct Writer<W: io::Write> {
     pub(crate) max_records_in_commit: NonZeroU16,
 
     pub(crate) offset_index_head: Option<OffsetIndexWriter>,
+
+    pub(crate) segment_checksum: SegmentChecksum,
 }
 
 impl<W: io::Write> Writer<W> {
@@ -139,6 +163,7 @@ impl<W: io::Write> Writer<W> {
         }
         let checksum = self.commit.write(&mut self.inner)?;
         self.inner.flush()?;
+        self.segment_checksum.update(checksum);
 
         let commit_len = self.commit.encoded_len() as u64;
         self.offset_index_head.as_mut().map(|index| {
@@ -204,6 +229,14 @@ impl<W: io::Write> Writer<W> {
     pub fn len(&self) -> u64 {
         self.bytes_written
     }
+
+    /// The [`SegmentChecksum`] over all commits in this segment.
+    ///
+    /// If the writer was resumed, this includes the commits which were in the
+    /// segment before resuming.
+    pub fn segment_checksum(&self) -> u32 {
+        self.segment_checksum.get()
+    }
 }
 
 pub trait FileLike {
@@ -364,6 +397,8 @@ impl<R: io::Read + io::Seek> Reader<R> {
         Commits {
             header: self.header,
             reader: io::BufReader::new(self.inner),
+            policy: CorruptionPolicy::default(),
+            skipped: 0,
         }
     }
 
@@ -463,16 +498,71 @@ pub struct Transaction<T> {
     pub txdata: T,
 }
 
+/// What [`Commits`] does when it encounters a commit whose checksum doesn't
+/// match its contents.
+///
+/// A corrupt commit is read in full before its checksum is verified, so the
+/// reader is positioned at the next commit boundary afterwards, as determined
+/// by the length stored in the commit header. The skipping policies resume
+/// from there. Note that if the corruption affects the length field itself,
+/// the subsequent commits will most likely fail to decode as well.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CorruptionPolicy {
+    /// Yield an error for the first corrupt commit.
+    #[default]
+    StopAtFirst,
+    /// Skip all corrupt commits.
+    Skip,
+    /// Skip up to the given number of corrupt commits, and yield an error for
+    /// the next one.
+    SkipUpTo(usize),
+}
+
 pub struct Commits<R> {
     pub header: Header,
     reader: io::BufReader<R>,
+    policy: CorruptionPolicy,
+    skipped: usize,
+}
+
+impl<R> Commits<R> {
+    /// Set the [`CorruptionPolicy`] of this iterator.
+    ///
+    /// The default is [`CorruptionPolicy::StopAtFirst`].
+    pub fn with_corruption_policy(self, policy: CorruptionPolicy) -> Self {
+        Self { policy, ..self }
+    }
+
+    /// The number of corrupt commits skipped so far.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    fn should_skip(&self, e: &io::Error) -> bool {
+        let corrupt = e.kind() == io::ErrorKind::InvalidData
+            && e.get_ref().is_some_and(|inner| inner.is::<error::ChecksumMismatch>());
+        corrupt
+            && match self.policy {
+                CorruptionPolicy::StopAtFirst => false,
+                CorruptionPolicy::Skip => true,
+                CorruptionPolicy::SkipUpTo(max) => self.skipped < max,
+            }
+    }
 }
 
 impl<R: io::Read> Iterator for Commits<R> {
     type Item = io::Result<StoredCommit>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        StoredCommit::decode_internal(&mut self.reader, self.header.log_format_version).transpose()
+        loop {
+            match StoredCommit::decode_internal(&mut self.reader, self.header.log_format_version) {
+                Err(e) if self.should_skip(&e) => {
+                    warn!("skipping corrupt commit: {e}");
+                    self.skipped += 1;
+                }
+                res => return res.transpose(),
+            }
+        }
     }
 }
 
@@ -510,6 +600,8 @@ pub struct Metadata {
     pub tx_range: Range<u64>,
     pub size_in_bytes: u64,
     pub max_epoch: u64,
+    /// The [`SegmentChecksum`] over all commits traversed.
+    pub checksum: SegmentChecksum,
 }
 
 impl Metadata {
@@ -536,13 +628,15 @@ impl Metadata {
             },
             size_in_bytes: Header::LEN as u64,
             max_epoch: Commit::DEFAULT_EPOCH,
+            checksum: SegmentChecksum::default(),
         };
 
         fn commit_meta<R: io::Read>(
             reader: &mut R,
             sofar: &Metadata,
-        ) -> Result<Option<commit::Metadata>, error::SegmentMetadata> {
-            commit::Metadata::extract(reader).map_err(|e| {
+        ) -> Result<Option<(commit::Metadata, u32)>, error::SegmentMetadata> {
+            let commit = Commit::decode_with_crc(reader).map(|x| x.map(|(commit, crc)| (commit.into(), crc)));
+            commit.map_err(|e| {
                 if matches!(e.kind(), io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof) {
                     error::SegmentMetadata::InvalidCommit {
                         sofar: sofar.clone(),
@@ -553,7 +647,7 @@ impl Metadata {
                 }
             })
         }
-        while let Some(commit) = commit_meta(&mut reader, &sofar)? {
+        while let Some((commit, crc)) = commit_meta(&mut reader, &sofar)? {
             debug!("commit::{commit:?}");
             if commit.tx_range.start != sofar.tx_range.end {
                 return Err(io::Error::new(
@@ -569,6 +663,7 @@ impl Metadata {
             sofar.size_in_bytes += commit.size_in_bytes;
             // TODO: Should it be an error to encounter an epoch going backwards?
             sofar.max_epoch = commit.epoch.max(sofar.max_epoch);
+            sofar.checksum.update(crc);
         }
 
         Ok(sofar)
@@ -650,6 +745,7 @@ mod tests {
             tx_range,
             size_in_bytes,
             max_epoch: _,
+            checksum: _,
         } = reader.metadata().unwrap();
 
         assert_eq!(tx_range.start, 0);
@@ -738,6 +834,7 @@ mod tests {
                 max_records_in_commit,
 
                 offset_index_head: None,
+                segment_checksum: SegmentChecksum::default(),
             };
 
             for i in 0..max_records_in_commit.get() {
@@ -756,6 +853,85 @@ mod tests {
         }
     }
 
+    #[test]
+    fn corruption_policy() {
+        let mut buf = Vec::new();
+        Header::default().write(&mut buf).unwrap();
+        let mut records_pos = Vec::new();
+        for i in 0..5 {
+            records_pos.push(buf.len() + commit::Header::LEN);
+            Commit {
+                min_tx_offset: i * 2,
+                epoch: Commit::DEFAULT_EPOCH,
+                n: 2,
+                records: vec![i as u8; 32],
+            }
+            .write(&mut buf)
+            .unwrap();
+        }
+        // Corrupt the commits at offsets 2 and 6.
+        buf[records_pos[1]] ^= 0xff;
+        buf[records_pos[3]] ^= 0xff;
+
+        let read = |policy| {
+            let reader = Reader::new(DEFAULT_LOG_FORMAT_VERSION, 0, io::Cursor::new(buf.clone())).unwrap();
+            let mut offsets = Vec::new();
+            for commit in reader.commits().with_corruption_policy(policy) {
+                match commit {
+                    Ok(commit) => offsets.push(commit.min_tx_offset),
+                    Err(e) => {
+                        assert!(e.into_inner().unwrap().is::<error::ChecksumMismatch>());
+                        return (offsets, true);
+                    }
+                }
+            }
+            (offsets, false)
+        };
+
+        assert_eq!(read(CorruptionPolicy::StopAtFirst), (vec![0], true));
+        assert_eq!(read(CorruptionPolicy::Skip), (vec![0, 4, 8], false));
+        assert_eq!(read(CorruptionPolicy::SkipUpTo(1)), (vec![0, 4], true));
+        assert_eq!(read(CorruptionPolicy::SkipUpTo(2)), (vec![0, 4, 8], false));
+    }
+
+    #[test]
+    fn segment_checksum() {
+        let repo = repo::Memory::default();
+
+        let mut writer = repo::create_segment_writer(&repo, Options::default(), Commit::DEFAULT_EPOCH, 0).unwrap();
+        for i in 0..4 {
+            writer.append([i; 32]).unwrap();
+            writer.commit().unwrap();
+        }
+        let expected = writer.segment_checksum();
+        assert_ne!(expected, SegmentChecksum::default().get());
+
+        let accumulate = |checksums: &[u32]| {
+            let mut acc = SegmentChecksum::default();
+            for &crc in checksums {
+                acc.update(crc);
+            }
+            acc.get()
+        };
+        let reader = repo::open_segment_reader(&repo, DEFAULT_LOG_FORMAT_VERSION, 0).unwrap();
+        let stored = reader.commits().collect::<io::Result<Vec<_>>>().unwrap();
+        let mut checksums = stored.iter().map(|commit| commit.checksum).collect::<Vec<_>>();
+        let mut commits = stored.into_iter().map(Commit::from).collect::<Vec<_>>();
+        assert_eq!(accumulate(&checksums), expected);
+
+        // Resuming picks up the checksum of the existing commits.
+        let writer = repo::resume_segment_writer(&repo, Options::default(), 0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(writer.segment_checksum(), expected);
+
+        // A commit which was altered and re-checksummed passes its own
+        // checksum verification, but not the segment's.
+        commits[2].records[0] ^= 0xff;
+        checksums[2] = commits[2].write(io::sink()).unwrap();
+        assert_ne!(accumulate(&checksums), expected);
+    }
+
     #[test]
     fn next_tx_offset() {
         let mut writer = Writer {
@@ -767,6 +943,7 @@ mod tests {
 
             max_records_in_commit: NonZeroU16::MAX,
             offset_index_head: None,
+            segment_checksum: SegmentChecksum::default(),
         };
 
         assert_eq!(0, writer.next_tx_offset());
@@ -2,7 +2,57 @@ mod writer;
 pub use writer::{OnTrailingData, StreamWriter};
 
 mod reader;
-pub use reader::{commits, retain_range};
+pub use reader::{commits, commits_with_progress, retain_range};
+
+mod copy;
+pub use copy::{copy_range, Verify};
+
+mod seek;
+pub use seek::seek_to_offset;
+
+mod budgeted;
+pub use budgeted::decode_async_budgeted;
+
+mod offload;
+pub use offload::write_commit_async;
+
+mod reverse;
+pub use reverse::commits_reverse;
+
+mod append_only;
+pub use append_only::AppendOnlyWriter;
+
+mod dedup;
+pub use dedup::dedup_by_offset;
+
+mod shared;
+pub use shared::{ReadAt, SharedSegment, SharedSegmentReader};
+
+mod cached_len;
+pub use cached_len::CachedLenWriter;
+
+mod positions;
+pub use positions::commits_with_byte_pos;
+
+mod intent;
+pub use intent::{find_torn_commit, write_commit_with_intent, TornCommit};
+
+mod replay;
+pub use replay::{replay_with_cursor, resume_replay, ReplayCursor};
+
+mod merge;
+pub use merge::merge_segments;
+
+mod gap;
+pub use gap::{limit_offset_gap, StreamItem};
+
+mod fanout;
+pub use fanout::{shared_commits, SharedCommits, Subscriber};
+
+mod chain;
+pub use chain::SegmentChainWriter;
 
 mod common;
-pub use common::{AsyncLen, IntoAsyncSegment, RangeFromMaybeToInclusive};
+pub use common::{
+    at_segment_end, AsyncFsyncBarrier, AsyncLen, IntoAsyncSegment, RangeFromMaybeToInclusive, SegmentEnd,
+};
@@ -0,0 +1,135 @@
+use std::{
+    io::{self, SeekFrom},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncSeek, AsyncWrite};
+
+use super::common::{AsyncFsync, AsyncLen};
+
+/// A segment writer which only permits appending.
+///
+/// Wraps a writer obtained via [`super::IntoAsyncSegment::into_async_writer`],
+/// which must be positioned at the end of the segment, and keeps track of the
+/// append position itself. This makes [`AsyncLen::segment_len`] O(1).
+///
+/// The [`AsyncSeek`] impl only accepts seeks to the current append position,
+/// and returns an error of kind [`io::ErrorKind::InvalidInput`] for any other
+/// seek. The underlying writer is never seeked.
+pub struct AppendOnlyWriter<W> {
+    inner: W,
+    pos: u64,
+}
+
+impl<W: AsyncLen> AppendOnlyWriter<W> {
+    /// Wrap `inner`, taking its current [`AsyncLen::segment_len`] as the
+    /// append position.
+    pub async fn new(mut inner: W) -> io::Result<Self> {
+        let pos = inner.segment_len().await?;
+        Ok(Self { inner, pos })
+    }
+}
+
+impl<W> AppendOnlyWriter<W> {
+    /// The position at which the next write will append.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Consume `self`, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for AppendOnlyWriter<W> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let res = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = res {
+            this.pos += n as u64;
+        }
+        res
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl<W: Unpin> AsyncSeek for AppendOnlyWriter<W> {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let target = match position {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(delta) | SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        if target != Some(self.pos) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("append-only writer at {} cannot seek to {:?}", self.pos, position),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.pos))
+    }
+}
+
+impl<W: AsyncLen + Send> AsyncLen for AppendOnlyWriter<W> {
+    async fn segment_len(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+impl<W: AsyncFsync + Sync> AsyncFsync for AppendOnlyWriter<W> {
+    async fn fsync(&self) {
+        self.inner.fsync().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncSeekExt as _, AsyncWriteExt as _};
+
+    use super::*;
+    use crate::{repo::mem, stream::IntoAsyncSegment as _};
+
+    #[tokio::test]
+    async fn rejects_rewind() {
+        let segment = mem::Segment::default();
+        let mut writer = AppendOnlyWriter::new(segment.clone().into_async_writer())
+            .await
+            .unwrap();
+
+        writer.write_all(b"hello").await.unwrap();
+        writer.write_all(b", world").await.unwrap();
+        assert_eq!(writer.segment_len().await.unwrap(), 12);
+
+        // Seeking to the append position is a no-op.
+        assert_eq!(writer.seek(SeekFrom::End(0)).await.unwrap(), 12);
+        assert_eq!(writer.seek(SeekFrom::Start(12)).await.unwrap(), 12);
+        // Anything else is an error.
+        for pos in [SeekFrom::Start(0), SeekFrom::Current(-5), SeekFrom::End(1)] {
+            let err = writer.seek(pos).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+
+        writer.write_all(b"!").await.unwrap();
+        writer.flush().await.unwrap();
+        writer.fsync().await;
+        assert_eq!(segment.buf_mut().as_slice(), b"hello, world!");
+    }
+}
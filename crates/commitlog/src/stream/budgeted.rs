@@ -0,0 +1,120 @@
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt as _};
+
+use crate::{
+    commit::{self, Checksummer as _, Crc32cHasher},
+    error::ChecksumMismatch,
+    Commit,
+};
+
+use super::common::read_exact;
+
+/// Attempt to read one [`Commit`] from `reader`, yielding to the async
+/// runtime after every `budget_bytes` read.
+///
+/// Reading a huge commit from a reader which is always ready, e.g. one
+/// backed by memory or a page cache, would otherwise block the executor
+/// until the whole commit is read. Here, the records are read in chunks of at
+/// most `budget_bytes`, and [`tokio::task::yield_now`] is awaited after each
+/// chunk. A `budget_bytes` of zero is treated as one.
+///
+/// Otherwise behaves like [`Commit::decode`]: returns `None` if `reader` is
+/// at EOF, or the commit header is all zeroes, and verifies the checksum of
+/// the commit.
+pub async fn decode_async_budgeted<R>(reader: &mut R, budget_bytes: usize) -> io::Result<Option<Commit>>
+where
+    R: AsyncRead + Unpin,
+{
+    let budget_bytes = budget_bytes.max(1);
+    let mut crc = Crc32cHasher::default();
+
+    let mut header = [0; commit::Header::LEN];
+    if read_exact(reader, &mut header).await?.is_eof() {
+        return Ok(None);
+    }
+    let Some(hdr) = commit::Header::decode(&header[..])? else {
+        return Ok(None);
+    };
+    crc.update(&header);
+
+    let mut records = vec![0; hdr.len as usize];
+    for chunk in records.chunks_mut(budget_bytes) {
+        reader.read_exact(chunk).await?;
+        crc.update(chunk);
+        tokio::task::yield_now().await;
+    }
+
+    let mut checksum = [0; Commit::CHECKSUM_LEN];
+    reader.read_exact(&mut checksum).await?;
+    if crc.checksum() != u32::from_le_bytes(checksum) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, ChecksumMismatch));
+    }
+
+    Ok(Some(Commit {
+        min_tx_offset: hdr.min_tx_offset,
+        epoch: hdr.epoch,
+        n: hdr.n,
+        records,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn yields_while_decoding_large_commit() {
+        let commit = Commit {
+            min_tx_offset: 42,
+            epoch: Commit::DEFAULT_EPOCH,
+            n: 1,
+            records: (0..1024 * 1024).map(|i| i as u8).collect(),
+        };
+        let mut buf = Vec::new();
+        commit.write(&mut buf).unwrap();
+
+        // On the single-threaded test runtime, this task only gets to run
+        // when the decoder yields.
+        let polls = Arc::new(AtomicUsize::new(0));
+        let ticker = tokio::spawn({
+            let polls = polls.clone();
+            async move {
+                loop {
+                    polls.fetch_add(1, Ordering::Relaxed);
+                    tokio::task::yield_now().await;
+                }
+            }
+        });
+
+        let mut reader = buf.as_slice();
+        let decoded = decode_async_budgeted(&mut reader, 64 * 1024).await.unwrap().unwrap();
+        ticker.abort();
+
+        assert_eq!(decoded, commit);
+        assert!(reader.is_empty());
+        assert!(polls.load(Ordering::Relaxed) >= 8, "decoder yielded too rarely");
+        assert!(decode_async_budgeted(&mut reader, 1).await.unwrap().is_none());
+
+        // Corruption is detected.
+        let last = buf.len() - 1;
+        buf[last] ^= 0xff;
+        let err = decode_async_budgeted(&mut buf.as_slice(), 1024).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn empty_input_decodes_to_none() {
+        let empty: &[u8] = &[];
+        assert!(decode_async_budgeted(&mut { empty }, 1024).await.unwrap().is_none());
+        assert_eq!(
+            crate::stream::at_segment_end(&mut { empty }).await.unwrap(),
+            crate::stream::SegmentEnd::End
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::io::AsyncWrite;
+
+use super::common::{AsyncFsync, AsyncLen};
+
+/// A segment writer which caches the result of [`AsyncLen::segment_len`].
+///
+/// Determining the length of a file may require seeking, which is wasteful
+/// when the length is queried repeatedly, e.g. in a flush loop. The length
+/// obtained from the underlying writer is retained until the next write, so
+/// consecutive calls to [`AsyncLen::segment_len`] without intermittent writes
+/// are O(1).
+///
+/// Any call to [`AsyncWrite::poll_write`] invalidates the cached length, so a
+/// stale length is never returned. Note, however, that writes to the segment
+/// which don't go through this writer are not observed.
+pub struct CachedLenWriter<W> {
+    inner: W,
+    len: Option<u64>,
+}
+
+impl<W> CachedLenWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, len: None }
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Consume `self`, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for CachedLenWriter<W> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        this.len = None;
+        Pin::new(&mut this.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl<W: AsyncLen + Send> AsyncLen for CachedLenWriter<W> {
+    async fn segment_len(&mut self) -> io::Result<u64> {
+        if let Some(len) = self.len {
+            return Ok(len);
+        }
+        let len = self.inner.segment_len().await?;
+        self.len = Some(len);
+        Ok(len)
+    }
+}
+
+impl<W: AsyncFsync + Sync> AsyncFsync for CachedLenWriter<W> {
+    async fn fsync(&self) {
+        self.inner.fsync().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt as _;
+
+    use super::*;
+    use crate::repo::mem;
+
+    /// Counts the calls to [`AsyncLen::segment_len`].
+    struct CountingLen {
+        segment: mem::Segment,
+        calls: usize,
+    }
+
+    impl AsyncWrite for CountingLen {
+        fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.segment).poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.segment).poll_flush(cx)
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.segment).poll_shutdown(cx)
+        }
+    }
+
+    impl AsyncLen for CountingLen {
+        async fn segment_len(&mut self) -> io::Result<u64> {
+            self.calls += 1;
+            self.segment.segment_len().await
+        }
+    }
+
+    #[tokio::test]
+    async fn invalidates_on_write() {
+        let mut writer = CachedLenWriter::new(CountingLen {
+            segment: mem::Segment::default(),
+            calls: 0,
+        });
+
+        writer.write_all(b"hello").await.unwrap();
+        assert_eq!(writer.segment_len().await.unwrap(), 5);
+        assert_eq!(writer.segment_len().await.unwrap(), 5);
+        assert_eq!(writer.get_ref().calls, 1);
+
+        writer.write_all(b", world").await.unwrap();
+        assert_eq!(writer.segment_len().await.unwrap(), 12);
+        assert_eq!(writer.segment_len().await.unwrap(), 12);
+        assert_eq!(writer.get_ref().calls, 2);
+    }
+}
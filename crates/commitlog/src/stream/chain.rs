@@ -0,0 +1,248 @@
+use std::io;
+
+use log::trace;
+use tokio::{io::AsyncWriteExt as _, task::spawn_blocking};
+
+use crate::{repo::Repo, segment, Commit};
+
+use super::{AsyncFsyncBarrier as _, IntoAsyncSegment};
+
+/// Append commits to a chain of segments, rolling over to a new segment when
+/// the current one is full.
+///
+/// A new segment is created in `repo` when appending the next commit would
+/// make the current segment exceed `max_segment_size` bytes. Commits are never
+/// split across segments, so a single commit larger than `max_segment_size`
+/// is written to a segment of its own.
+///
+/// The previous segment is flushed and synced to disk before a new one is
+/// created. New segments are not preallocated or zeroed: segments are written
+/// in append mode, so any preallocated space would precede, rather than be
+/// overwritten by, the commits appended to it.
+///
+/// If appending or syncing fails, the current segment may end in a partially
+/// written commit. The writer is then poisoned, and all further calls return
+/// an error of the same kind.
+pub struct SegmentChainWriter<R>
+where
+    R: Repo + Send + 'static,
+    R::Segment: IntoAsyncSegment,
+{
+    repo: R,
+    max_segment_size: u64,
+
+    current_segment: Option<ChainedSegment<<R::Segment as IntoAsyncSegment>::AsyncSegmentWriter>>,
+    commit_buf: Vec<u8>,
+    poisoned: Option<io::ErrorKind>,
+}
+
+struct ChainedSegment<W> {
+    /// The minimum transaction offset of the segment.
+    offset: u64,
+    writer: W,
+    /// The length of the segment, including data not flushed yet.
+    len: u64,
+}
+
+impl<R> SegmentChainWriter<R>
+where
+    R: Repo + Send + 'static,
+    R::Segment: IntoAsyncSegment,
+{
+    /// Create a new [`SegmentChainWriter`] writing to `repo`.
+    ///
+    /// No segment is created until the first commit is appended.
+    pub fn new(repo: R, max_segment_size: u64) -> Self {
+        Self {
+            repo,
+            max_segment_size,
+            current_segment: None,
+            commit_buf: Vec::new(),
+            poisoned: None,
+        }
+    }
+
+    /// The minimum transaction offset of the segment currently written to,
+    /// if any.
+    pub fn current_segment_offset(&self) -> Option<u64> {
+        self.current_segment.as_ref().map(|segment| segment.offset)
+    }
+
+    /// Append `commit` to the current segment, or to a new segment if it
+    /// doesn't fit.
+    ///
+    /// The commit is not flushed, see [`Self::fsync_barrier`].
+    pub async fn append(&mut self, commit: &Commit) -> io::Result<()> {
+        self.check_poisoned()?;
+        self.commit_buf.clear();
+        commit.write(&mut self.commit_buf)?;
+
+        let res = self.append_commit_buf(commit.min_tx_offset).await;
+        self.poison_on_error(res)
+    }
+
+    /// Flush and sync the current segment to disk.
+    ///
+    /// Returns the length of the current segment, i.e. the byte offset up to
+    /// which it is guaranteed to be durable, or zero if no segment was
+    /// created yet.
+    ///
+    /// This is [`super::AsyncFsyncBarrier::fsync_barrier`] on the writer of
+    /// the current segment.
+    pub async fn fsync_barrier(&mut self) -> io::Result<u64> {
+        self.check_poisoned()?;
+        let res = match self.current_segment.as_mut() {
+            Some(current) => current.writer.fsync_barrier().await,
+            None => Ok(0),
+        };
+        self.poison_on_error(res)
+    }
+
+    async fn append_commit_buf(&mut self, min_tx_offset: u64) -> io::Result<()> {
+        let commit_len = self.commit_buf.len() as u64;
+        let mut current = match self.current_segment.take() {
+            Some(current)
+                if current.len == segment::Header::LEN as u64 || current.len + commit_len <= self.max_segment_size =>
+            {
+                current
+            }
+            mut full => {
+                if let Some(segment) = full.as_mut() {
+                    trace!("segment {} full at {} bytes", segment.offset, segment.len);
+                    if let Err(e) = segment.writer.fsync_barrier().await {
+                        self.current_segment = full;
+                        return Err(e);
+                    }
+                }
+                match self.create_segment(min_tx_offset).await {
+                    Ok(new) => new,
+                    Err(e) => {
+                        self.current_segment = full;
+                        return Err(e);
+                    }
+                }
+            }
+        };
+        let res = current.writer.write_all(&self.commit_buf).await;
+        if res.is_ok() {
+            current.len += commit_len;
+        }
+        self.current_segment = Some(current);
+
+        res
+    }
+
+    fn check_poisoned(&self) -> io::Result<()> {
+        match self.poisoned {
+            Some(kind) => Err(io::Error::new(kind, "segment chain writer failed previously")),
+            None => Ok(()),
+        }
+    }
+
+    fn poison_on_error<T>(&mut self, res: io::Result<T>) -> io::Result<T> {
+        if let Err(e) = &res {
+            self.poisoned = Some(e.kind());
+        }
+        res
+    }
+
+    async fn create_segment(
+        &self,
+        offset: u64,
+    ) -> io::Result<ChainedSegment<<R::Segment as IntoAsyncSegment>::AsyncSegmentWriter>> {
+        trace!("create segment at {offset}");
+        let repo = self.repo.clone();
+        let mut writer = spawn_blocking(move || repo.create_segment(offset))
+            .await
+            .map_err(io::Error::other)??
+            .into_async_writer();
+        let mut header = [0; segment::Header::LEN];
+        segment::Header::default().write(&mut header[..])?;
+        writer.write_all(&header).await?;
+
+        Ok(ChainedSegment {
+            offset,
+            writer,
+            len: segment::Header::LEN as u64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{repo, tests::helpers::enable_logging};
+
+    #[tokio::test]
+    async fn spans_two_segments() {
+        enable_logging();
+
+        let commits = (0..5)
+            .map(|i| Commit {
+                min_tx_offset: i * 2,
+                epoch: Commit::DEFAULT_EPOCH,
+                n: 2,
+                records: vec![i as u8; 64],
+            })
+            .collect::<Vec<_>>();
+        let commit_len = commits[0].encoded_len() as u64;
+        // Room for three commits per segment.
+        let max_segment_size = segment::Header::LEN as u64 + 3 * commit_len + 1;
+
+        let repo = repo::Memory::new();
+        let mut writer = SegmentChainWriter::new(repo.clone(), max_segment_size);
+        assert_eq!(writer.fsync_barrier().await.unwrap(), 0);
+        for commit in &commits {
+            writer.append(commit).await.unwrap();
+        }
+        assert_eq!(writer.current_segment_offset(), Some(6));
+        assert_eq!(
+            writer.fsync_barrier().await.unwrap(),
+            segment::Header::LEN as u64 + 2 * commit_len
+        );
+
+        assert_eq!(repo.existing_offsets().unwrap(), [0, 6]);
+        for (offset, expected) in [(0, &commits[..3]), (6, &commits[3..])] {
+            let mut segment = repo.open_segment(offset).unwrap();
+            assert!(repo::Segment::segment_len(&mut segment).unwrap() <= max_segment_size);
+            segment::Header::decode(&mut segment).unwrap();
+            let mut decoded = Vec::new();
+            while let Some(commit) = Commit::decode(&mut segment).unwrap() {
+                decoded.push(commit);
+            }
+            assert_eq!(decoded, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn poisoned_after_failed_rollover() {
+        enable_logging();
+
+        let commits = (0..2)
+            .map(|i| Commit {
+                min_tx_offset: i * 2,
+                epoch: Commit::DEFAULT_EPOCH,
+                n: 2,
+                records: vec![i as u8; 64],
+            })
+            .collect::<Vec<_>>();
+        let max_segment_size = segment::Header::LEN as u64 + commits[0].encoded_len() as u64;
+
+        let repo = repo::Memory::new();
+        // Occupy the offset of the second segment.
+        io::Write::write_all(&mut repo.create_segment(2).unwrap(), b"occupied").unwrap();
+
+        let mut writer = SegmentChainWriter::new(repo.clone(), max_segment_size);
+        writer.append(&commits[0]).await.unwrap();
+        let err = writer.append(&commits[1]).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(writer.current_segment_offset(), Some(0));
+
+        for err in [
+            writer.append(&commits[1]).await.unwrap_err(),
+            writer.fsync_barrier().await.unwrap_err(),
+        ] {
+            assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        }
+    }
+}
@@ -6,10 +6,13 @@ use std::{
 
 use tokio::io::{
     AsyncBufRead, AsyncBufReadExt as _, AsyncRead, AsyncReadExt as _, AsyncSeek, AsyncSeekExt, AsyncWrite,
+    AsyncWriteExt as _,
 };
 
 use crate::commit;
 
+use super::{ReadAt, SharedSegment};
+
 /// How to convert [`crate::repo::Segment`]s into async I/O types.
 pub trait IntoAsyncSegment {
     type AsyncSegmentReader: AsyncBufRead + AsyncSeek + Unpin + Send;
@@ -17,6 +20,17 @@ pub trait IntoAsyncSegment {
 
     fn into_async_reader(self) -> Self::AsyncSegmentReader;
     fn into_async_writer(self) -> Self::AsyncSegmentWriter;
+
+    /// Convert into a handle from which independent readers can be created,
+    /// without reopening the segment for each of them.
+    ///
+    /// Only available for segments supporting positioned reads.
+    fn into_shared(self) -> SharedSegment<Self>
+    where
+        Self: ReadAt + Sized,
+    {
+        SharedSegment::new(self)
+    }
 }
 
 impl IntoAsyncSegment for std::fs::File {
@@ -103,6 +117,29 @@ impl AsyncLen for crate::repo::mem::Segment {
     }
 }
 
+/// Flush and sync a segment writer in one step.
+///
+/// Implemented for all segment writers, i.e. types implementing [`AsyncWrite`],
+/// [`AsyncFsync`] and [`AsyncLen`].
+pub trait AsyncFsyncBarrier: AsyncWrite + AsyncFsync + AsyncLen + Unpin + Send {
+    /// Flush any buffered data, and fsync the segment.
+    ///
+    /// Returns the length of the segment at the time of the fsync, i.e. the
+    /// byte offset up to which the segment is guaranteed to be durable. As
+    /// the method takes `&mut self`, no writes can occur in between flushing,
+    /// determining the length and the fsync.
+    fn fsync_barrier(&mut self) -> impl Future<Output = io::Result<u64>> + Send {
+        async {
+            self.flush().await?;
+            let len = self.segment_len().await?;
+            self.fsync().await;
+            Ok(len)
+        }
+    }
+}
+
+impl<T: AsyncWrite + AsyncFsync + AsyncLen + Unpin + Send> AsyncFsyncBarrier for T {}
+
 /// An optionally half-open range.
 ///
 /// Can express both `start..=end` and `start..`.
@@ -153,6 +190,10 @@ impl RangeBounds<u64> for RangeFromMaybeToInclusive {
     }
 }
 
+/// The number of bytes read between yields to the runtime, see
+/// [`super::decode_async_budgeted`].
+pub(super) const READ_BUDGET: usize = 64 * 1024;
+
 #[derive(Default)]
 pub(super) struct CommitBuf {
     pub header: [u8; commit::Header::LEN],
@@ -199,3 +240,107 @@ pub(super) async fn peek_buf(src: &mut (impl AsyncBufRead + Unpin)) -> io::Resul
     let buf = src.fill_buf().await?;
     Ok(if buf.is_empty() { None } else { Some(buf) })
 }
+
+/// Whether a segment reader is at the end of the commits in the segment,
+/// as determined by [`at_segment_end`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SegmentEnd {
+    /// The reader is at EOF, or the next [`commit::Header::LEN`] bytes are
+    /// all zeroes, i.e. preallocated space.
+    End,
+    /// The next bytes are not all zeroes, so a commit header follows.
+    NotEnd,
+    /// Fewer than [`commit::Header::LEN`] bytes are buffered, and they are
+    /// all zeroes.
+    ///
+    /// Whether they are followed by more zeroes, EOF or a non-zero byte can't
+    /// be determined without consuming input. Callers should decode the next
+    /// commit header as usual, which yields no commit in the first two cases.
+    Unknown,
+}
+
+/// Determine if `src` is at the end of the commits in a segment, without
+/// consuming any input.
+///
+/// Only the bytes in the buffer of `src` are inspected, filling it if it is
+/// empty. See [`SegmentEnd`] for the possible outcomes.
+pub async fn at_segment_end(src: &mut (impl AsyncBufRead + Unpin)) -> io::Result<SegmentEnd> {
+    let Some(buf) = peek_buf(src).await? else {
+        return Ok(SegmentEnd::End);
+    };
+    let head = &buf[..buf.len().min(commit::Header::LEN)];
+    Ok(if head.iter().any(|&b| b != 0) {
+        SegmentEnd::NotEnd
+    } else if head.len() == commit::Header::LEN {
+        SegmentEnd::End
+    } else {
+        SegmentEnd::Unknown
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::mem;
+
+    #[tokio::test]
+    async fn fsync_barrier_returns_written_len() {
+        let segment = mem::Segment::default();
+        let mut writer = segment.clone().into_async_writer();
+
+        writer.write_all(&[42; 100]).await.unwrap();
+        // Not flushed yet.
+        assert!(segment.is_empty());
+        assert_eq!(writer.fsync_barrier().await.unwrap(), 100);
+        assert_eq!(segment.len(), 100);
+
+        writer.write_all(&[42; 28]).await.unwrap();
+        assert_eq!(writer.fsync_barrier().await.unwrap(), 128);
+    }
+
+    #[tokio::test]
+    async fn at_segment_end_detects_padding_and_eof() {
+        let commit = crate::Commit {
+            min_tx_offset: 0,
+            epoch: crate::Commit::DEFAULT_EPOCH,
+            n: 1,
+            records: vec![1; 32],
+        };
+        let mut buf = Vec::new();
+        commit.write(&mut buf).unwrap();
+
+        // True EOF.
+        let mut src = buf.as_slice();
+        assert_eq!(at_segment_end(&mut src).await.unwrap(), SegmentEnd::NotEnd);
+        src.consume(commit.encoded_len());
+        assert_eq!(at_segment_end(&mut src).await.unwrap(), SegmentEnd::End);
+
+        // Trailing zero padding.
+        buf.extend_from_slice(&[0; 64]);
+        let mut src = buf.as_slice();
+        assert_eq!(at_segment_end(&mut src).await.unwrap(), SegmentEnd::NotEnd);
+        src.consume(commit.encoded_len());
+        assert_eq!(at_segment_end(&mut src).await.unwrap(), SegmentEnd::End);
+        // Input is not consumed.
+        assert_eq!(src.len(), 64);
+    }
+
+    #[tokio::test]
+    async fn at_segment_end_is_unknown_for_short_zeroes() {
+        // Short zero tail at EOF.
+        let mut src = &[0; commit::Header::LEN - 1][..];
+        assert_eq!(at_segment_end(&mut src).await.unwrap(), SegmentEnd::Unknown);
+        assert!(crate::Commit::decode(src).unwrap().is_none());
+
+        // Short zero prefix of a commit header, followed by more input.
+        let mut buf = vec![0; commit::Header::LEN - 1];
+        buf.push(1);
+        let mut src = tokio::io::BufReader::with_capacity(8, buf.as_slice());
+        assert_eq!(at_segment_end(&mut src).await.unwrap(), SegmentEnd::Unknown);
+        assert_eq!(src.buffer(), &[0; 8]);
+
+        // Non-zero bytes are conclusive, even if fewer than a header.
+        let mut src = &[0, 0, 1][..];
+        assert_eq!(at_segment_end(&mut src).await.unwrap(), SegmentEnd::NotEnd);
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    io::{self, SeekFrom},
+    ops::Range,
+};
+
+use log::trace;
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncSeek, AsyncSeekExt as _, AsyncWrite, AsyncWriteExt as _};
+
+use crate::{commit, Commit, StoredCommit};
+
+use super::common::{read_exact, CommitBuf};
+
+/// Whether [`copy_range`] should verify the commits it copies.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Verify {
+    /// Copy the framed bytes as they are. This is the default.
+    #[default]
+    Skip,
+    /// Verify the checksum of each commit before copying it.
+    ///
+    /// A mismatch results in an error of kind [`io::ErrorKind::InvalidData`],
+    /// as returned by [`StoredCommit::decode`].
+    Checksum,
+}
+
+/// Copy the commits covering the transaction offsets `range` from `src` to
+/// `dst`.
+///
+/// `src` must be positioned at a commit boundary, e.g. right after the segment
+/// header. Only the commit headers are inspected in order to locate commit
+/// boundaries, and whole commits are copied verbatim, i.e. without decoding
+/// and re-encoding them.
+///
+/// Commits which end before `range.start` are skipped. Copying stops before
+/// the first commit starting at or after `range.end`, and `src` is left
+/// positioned at the start of that commit. Like [`commit::Header::decode`],
+/// EOF or an all-zeroes commit header also terminate the copy.
+///
+/// Note that only whole commits are copied, so a `range` that doesn't fall on
+/// commit boundaries may copy extra transactions.
+///
+/// Returns the number of bytes written to `dst`.
+pub async fn copy_range<R, W>(src: &mut R, dst: &mut W, range: Range<u64>, verify: Verify) -> io::Result<u64>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut commit_buf = CommitBuf::default();
+    let mut bytes_written = 0;
+
+    loop {
+        if read_exact(src, &mut commit_buf.header).await?.is_eof() {
+            trace!("eof reading commit header");
+            break;
+        }
+        let Some(hdr) = commit::Header::decode(&commit_buf.header[..])? else {
+            trace!("all-zeroes commit header");
+            break;
+        };
+        let body_len = hdr.len as usize + Commit::CHECKSUM_LEN;
+
+        // Stop at the commit boundary, so `src` can be used to continue
+        // reading from there.
+        if hdr.min_tx_offset >= range.end {
+            src.seek(SeekFrom::Current(-(commit::Header::LEN as i64))).await?;
+            break;
+        }
+        // Skip commits preceding the range.
+        if hdr.min_tx_offset + hdr.n as u64 <= range.start {
+            src.seek(SeekFrom::Current(body_len as i64)).await?;
+            continue;
+        }
+
+        commit_buf.body.resize(body_len, 0);
+        src.read_exact(&mut commit_buf.body).await?;
+        if verify == Verify::Checksum {
+            StoredCommit::decode(commit_buf.as_reader())?;
+        }
+
+        trace!("copying commit {}", hdr.min_tx_offset);
+        dst.write_all_buf(&mut commit_buf.as_buf()).await?;
+        bytes_written += commit_buf.filled_len() as u64;
+    }
+
+    Ok(bytes_written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::helpers::enable_logging;
+
+    fn commit(min_tx_offset: u64, byte: u8) -> Commit {
+        Commit {
+            min_tx_offset,
+            epoch: Commit::DEFAULT_EPOCH,
+            n: 1,
+            records: vec![byte; 64],
+        }
+    }
+
+    #[tokio::test]
+    async fn copy_middle_commit() {
+        enable_logging();
+
+        let commits = [commit(0, 1), commit(1, 2), commit(2, 3)];
+        let mut buf = Vec::new();
+        for commit in &commits {
+            commit.write(&mut buf).unwrap();
+        }
+
+        let mut src = io::Cursor::new(buf);
+        let mut dst = Vec::new();
+        let written = copy_range(&mut src, &mut dst, 1..2, Verify::Checksum).await.unwrap();
+
+        assert_eq!(written, commits[1].encoded_len() as u64);
+        assert_eq!(written, dst.len() as u64);
+        let copied = Commit::decode(&mut dst.as_slice()).unwrap().unwrap();
+        assert_eq!(copied, commits[1]);
+
+        // `src` is positioned at the start of the last commit.
+        let rest = Commit::decode(&mut src).unwrap().unwrap();
+        assert_eq!(rest, commits[2]);
+    }
+}
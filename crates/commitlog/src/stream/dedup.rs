@@ -0,0 +1,90 @@
+use std::io;
+
+use async_stream::try_stream;
+use futures::{Stream, TryStreamExt as _};
+use log::warn;
+
+use crate::Commit;
+
+/// Drop commits from `commits` which overlap with a commit yielded before.
+///
+/// A commit is considered a duplicate if its `min_tx_offset` is below the end
+/// of the transaction range of any commit already yielded. This can happen
+/// when reading segments which were copied, but not fully removed afterwards,
+/// e.g. due to a crash during compaction. The resulting stream is strictly
+/// monotonic wrt transaction offsets. Gaps in the sequence are not filled nor
+/// rejected, the commits following a gap are yielded as is.
+///
+/// Each dropped commit is logged, and passed to `on_duplicate`, e.g. to count
+/// them.
+pub fn dedup_by_offset<S>(
+    commits: S,
+    mut on_duplicate: impl FnMut(&Commit) + Send + 'static,
+) -> impl Stream<Item = io::Result<Commit>>
+where
+    S: Stream<Item = io::Result<Commit>> + Send + 'static,
+{
+    try_stream! {
+        let mut next_offset = None;
+        futures::pin_mut!(commits);
+        while let Some(commit) = commits.try_next().await? {
+            if next_offset.is_some_and(|next| commit.min_tx_offset < next) {
+                warn!(
+                    "dropping duplicate commit {:?}, expected offset {:?}",
+                    commit.tx_range(),
+                    next_offset
+                );
+                on_duplicate(&commit);
+                continue;
+            }
+            next_offset = Some(commit.tx_range().end);
+            yield commit;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use futures::stream;
+
+    use super::*;
+    use crate::tests::helpers::enable_logging;
+
+    #[tokio::test]
+    async fn drops_overlapping_commits() {
+        enable_logging();
+
+        let commit = |min_tx_offset| Commit {
+            min_tx_offset,
+            epoch: Commit::DEFAULT_EPOCH,
+            n: 2,
+            records: vec![min_tx_offset as u8; 8],
+        };
+        // Two overlapping segment copies, followed by a gap.
+        let input = [0, 2, 4, 2, 4, 6, 12, 12, 10]
+            .into_iter()
+            .map(|offset| Ok(commit(offset)))
+            .collect::<Vec<_>>();
+
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let deduped = dedup_by_offset(stream::iter(input), {
+            let dropped = dropped.clone();
+            move |_| {
+                dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        })
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+
+        let offsets = deduped.iter().map(|commit| commit.min_tx_offset).collect::<Vec<_>>();
+        assert_eq!(offsets, [0, 2, 4, 6, 12]);
+        assert!(deduped.windows(2).all(|w| w[0].tx_range().end <= w[1].min_tx_offset));
+        assert_eq!(dropped.load(Ordering::Relaxed), 4);
+    }
+}
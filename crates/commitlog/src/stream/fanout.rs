@@ -0,0 +1,208 @@
+use std::{
+    io,
+    ops::RangeBounds,
+    sync::{Arc, Mutex},
+};
+
+use log::trace;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt as _},
+    sync::broadcast,
+};
+
+use crate::{segment, Commit};
+
+use super::{common::READ_BUDGET, decode_async_budgeted, RangeFromMaybeToInclusive};
+
+/// Decodes the commits of a segment once, and sends them to any number of
+/// subscribers, see [`shared_commits`].
+pub struct SharedCommits<R> {
+    segment: R,
+    range: RangeFromMaybeToInclusive,
+    tx: broadcast::Sender<Arc<Commit>>,
+    subscriber: Subscriber,
+}
+
+/// A cloneable handle for subscribing to a [`SharedCommits`], also after
+/// [`SharedCommits::run`] has taken ownership of it.
+#[derive(Clone)]
+pub struct Subscriber {
+    tx: Arc<Mutex<Option<broadcast::Sender<Arc<Commit>>>>>,
+}
+
+impl Subscriber {
+    /// Subscribe to the commits sent from now on.
+    ///
+    /// If the [`SharedCommits`] has finished or was dropped, the returned
+    /// receiver is already closed.
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<Commit>> {
+        match self.tx.lock().unwrap().as_ref() {
+            Some(tx) => tx.subscribe(),
+            None => broadcast::channel(1).1,
+        }
+    }
+
+    fn close(&self) {
+        self.tx.lock().unwrap().take();
+    }
+}
+
+/// Prepare to decode the commits in `range` from `segment`, sharing each
+/// decoded commit with all subscribers as an [`Arc<Commit>`].
+///
+/// `segment` must be positioned at the start of a segment, i.e. before the
+/// segment header. A commit is in `range` if any of its transaction offsets
+/// is.
+///
+/// Subscribers are added via [`SharedCommits::subscribe`], or via a
+/// [`Subscriber`] handle obtained from [`SharedCommits::subscriber`] while
+/// decoding is in progress. They receive the commits sent after they
+/// subscribed. Decoding starts when the future
+/// returned by [`SharedCommits::run`] is polled. Subscribers which fall more
+/// than `capacity` commits behind miss the oldest commits, and are notified
+/// via [`broadcast::error::RecvError::Lagged`]. When all commits have been
+/// sent, or decoding fails, the channel is closed.
+pub fn shared_commits<R>(segment: R, range: impl RangeBounds<u64>, capacity: usize) -> SharedCommits<R>
+where
+    R: AsyncRead + Unpin,
+{
+    let (tx, _) = broadcast::channel(capacity);
+    SharedCommits {
+        segment,
+        range: RangeFromMaybeToInclusive::from_range_bounds(range),
+        subscriber: Subscriber {
+            tx: Arc::new(Mutex::new(Some(tx.clone()))),
+        },
+        tx,
+    }
+}
+
+impl<R: AsyncRead + Unpin> SharedCommits<R> {
+    /// Subscribe to the commits sent from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<Commit>> {
+        self.tx.subscribe()
+    }
+
+    /// A handle for subscribing once [`Self::run`] has been called.
+    pub fn subscriber(&self) -> Subscriber {
+        self.subscriber.clone()
+    }
+
+    /// Decode the commits in range and send them to the subscribers.
+    ///
+    /// Commits are decoded even if there are no subscribers at the time.
+    /// Returns the number of commits sent.
+    pub async fn run(mut self) -> io::Result<usize> {
+        let mut buf = [0u8; segment::Header::LEN];
+        self.segment.read_exact(&mut buf).await?;
+        segment::Header::decode(&buf[..])?;
+
+        let mut sent = 0;
+        while let Some(commit) = decode_async_budgeted(&mut self.segment, READ_BUDGET).await? {
+            if self.range.end.is_some_and(|end| commit.min_tx_offset > end) {
+                break;
+            }
+            if commit.tx_range().end <= self.range.start {
+                continue;
+            }
+            trace!("sending commit {}", commit.min_tx_offset);
+            // An error means there are no subscribers right now.
+            let _ = self.tx.send(Arc::new(commit));
+            sent += 1;
+        }
+
+        Ok(sent)
+    }
+}
+
+impl<R> Drop for SharedCommits<R> {
+    fn drop(&mut self) {
+        // Release the handles' sender, so the channel closes with ours.
+        self.subscriber.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt as _;
+
+    use crate::{repo::mem, stream::IntoAsyncSegment as _};
+
+    #[tokio::test]
+    async fn subscribers_share_decoded_commits() {
+        let segment = mem::Segment::default();
+        let mut writer = segment.clone();
+        segment::Header::default().write(&mut writer).unwrap();
+        for i in 0..5 {
+            Commit {
+                min_tx_offset: i * 2,
+                epoch: Commit::DEFAULT_EPOCH,
+                n: 2,
+                records: vec![i as u8; 32],
+            }
+            .write(&mut writer)
+            .unwrap();
+        }
+
+        let shared = shared_commits(segment.into_async_reader(), 3..=6, 8);
+        let (mut a, mut b) = (shared.subscribe(), shared.subscribe());
+        assert_eq!(shared.run().await.unwrap(), 3);
+
+        for min_tx_offset in [2, 4, 6] {
+            let (x, y) = (a.recv().await.unwrap(), b.recv().await.unwrap());
+            assert_eq!(x.min_tx_offset, min_tx_offset);
+            assert!(Arc::ptr_eq(&x, &y));
+        }
+        assert!(matches!(a.recv().await, Err(broadcast::error::RecvError::Closed)));
+        assert!(matches!(b.recv().await, Err(broadcast::error::RecvError::Closed)));
+    }
+
+    #[tokio::test]
+    async fn late_subscribers_receive_subsequent_commits() {
+        let commits = (0..5)
+            .map(|i| {
+                let mut buf = Vec::new();
+                Commit {
+                    min_tx_offset: i * 2,
+                    epoch: Commit::DEFAULT_EPOCH,
+                    n: 2,
+                    records: vec![i as u8; 32],
+                }
+                .write(&mut buf)
+                .unwrap();
+                buf
+            })
+            .collect::<Vec<_>>();
+        let (mut tx, rx) = tokio::io::duplex(4096);
+        let mut header = Vec::new();
+        segment::Header::default().write(&mut header).unwrap();
+        tx.write_all(&header).await.unwrap();
+        tx.write_all(&commits[..2].concat()).await.unwrap();
+
+        let shared = shared_commits(rx, .., 2);
+        let subscriber = shared.subscriber();
+        let mut a = subscriber.subscribe();
+        let run = tokio::spawn(shared.run());
+
+        assert_eq!(a.recv().await.unwrap().min_tx_offset, 0);
+        assert_eq!(a.recv().await.unwrap().min_tx_offset, 2);
+        // `run` is now waiting for more input.
+        let mut b = subscriber.subscribe();
+        tx.write_all(&commits[2..].concat()).await.unwrap();
+        drop(tx);
+
+        for min_tx_offset in [4, 6, 8] {
+            let (x, y) = (a.recv().await.unwrap(), b.recv().await.unwrap());
+            assert_eq!(x.min_tx_offset, min_tx_offset);
+            assert!(Arc::ptr_eq(&x, &y));
+        }
+        assert_eq!(run.await.unwrap().unwrap(), 5);
+        assert!(matches!(a.recv().await, Err(broadcast::error::RecvError::Closed)));
+        assert!(matches!(b.recv().await, Err(broadcast::error::RecvError::Closed)));
+        assert!(matches!(
+            subscriber.subscribe().recv().await,
+            Err(broadcast::error::RecvError::Closed)
+        ));
+    }
+}
@@ -0,0 +1,88 @@
+use std::io;
+
+use async_stream::try_stream;
+use futures::{Stream, TryStreamExt as _};
+use log::warn;
+
+use crate::Commit;
+
+/// An item of the stream returned by [`limit_offset_gap`].
+#[derive(Debug, PartialEq)]
+pub enum StreamItem {
+    /// The next commit.
+    Commit(Commit),
+    /// The offsets `from..to` are missing between the previous commit and the
+    /// next one, which exceeds the maximum gap. This is the last item.
+    GapTooLarge { from: u64, to: u64 },
+}
+
+/// Yield the commits from `commits` until the gap between two consecutive
+/// commits exceeds `max_offset_gap`.
+///
+/// The gap is the number of transaction offsets between the end of the
+/// transaction range of a commit and the `min_tx_offset` of the next commit.
+/// If it is larger than `max_offset_gap`, [`StreamItem::GapTooLarge`] is
+/// yielded and the stream ends. When tailing a replicated log, this signals
+/// that the follower fell too far behind, and must obtain a snapshot instead.
+///
+/// Commits overlapping the previous one are not considered a gap, they can
+/// be dropped via [`super::dedup_by_offset`] before applying this adapter.
+pub fn limit_offset_gap<S>(commits: S, max_offset_gap: u64) -> impl Stream<Item = io::Result<StreamItem>>
+where
+    S: Stream<Item = io::Result<Commit>> + Send + 'static,
+{
+    try_stream! {
+        let mut next_offset = None;
+        futures::pin_mut!(commits);
+        while let Some(commit) = commits.try_next().await? {
+            if let Some(from) = next_offset.filter(|&next| commit.min_tx_offset.saturating_sub(next) > max_offset_gap) {
+                let to = commit.min_tx_offset;
+                warn!("offset gap {from}..{to} exceeds maximum of {max_offset_gap}");
+                yield StreamItem::GapTooLarge { from, to };
+                break;
+            }
+            next_offset = Some(commit.tx_range().end);
+            yield StreamItem::Commit(commit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+
+    fn commit(min_tx_offset: u64) -> Commit {
+        Commit {
+            min_tx_offset,
+            epoch: Commit::DEFAULT_EPOCH,
+            n: 2,
+            records: vec![min_tx_offset as u8; 8],
+        }
+    }
+
+    async fn limit(input: &[u64], max_offset_gap: u64) -> Vec<StreamItem> {
+        let input = input.iter().map(|&offset| Ok(commit(offset))).collect::<Vec<_>>();
+        limit_offset_gap(stream::iter(input), max_offset_gap)
+            .try_collect()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn signals_gap_too_large() {
+        let items = limit(&[0, 2, 4, 10, 12], 3).await;
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[3], StreamItem::GapTooLarge { from: 6, to: 10 });
+
+        // A gap of exactly the maximum is tolerated.
+        let items = limit(&[0, 2, 6, 8], 2).await;
+        assert!(items.iter().all(|item| matches!(item, StreamItem::Commit(_))));
+        assert_eq!(items.len(), 4);
+
+        let items = limit(&[0, 2, 7, 9], 2).await;
+        assert_eq!(items.last(), Some(&StreamItem::GapTooLarge { from: 4, to: 7 }));
+        assert_eq!(items.len(), 3);
+    }
+}
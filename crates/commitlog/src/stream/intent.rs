@@ -0,0 +1,256 @@
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+
+use crate::{
+    commit::{self, Checksummer as _, Crc32cHasher},
+    error::ChecksumMismatch,
+    segment, Commit,
+};
+
+use super::common::AsyncFsync;
+
+/// Write `commit` to `out`, making its header durable before writing the
+/// body.
+///
+/// The commit header, which includes the length of the records, serves as a
+/// write intent: it is written, flushed and fsynced first. Only then are the
+/// records and checksum written. If the process crashes in between, the
+/// segment ends in a commit whose header is intact, but whose body is short
+/// of the announced length. [`find_torn_commit`] can distinguish this case
+/// from a complete commit with a corrupted body, which a checksum mismatch
+/// alone can't.
+///
+/// The written bytes are the same as those written by [`Commit::write`].
+/// Like it, returns the crc32 checksum of the commit on success. The body is
+/// not flushed.
+pub async fn write_commit_with_intent<W>(out: &mut W, commit: &Commit) -> io::Result<u32>
+where
+    W: AsyncWrite + AsyncFsync + Unpin,
+{
+    let mut buf = Vec::with_capacity(commit.encoded_len());
+    let crc = commit.write(&mut buf)?;
+    let (header, body) = buf.split_at(commit::Header::LEN);
+
+    out.write_all(header).await?;
+    out.flush().await?;
+    out.fsync().await;
+    out.write_all(body).await?;
+
+    Ok(crc)
+}
+
+/// An incompletely written commit at the end of a segment,
+/// as found by [`find_torn_commit`].
+#[derive(Debug, PartialEq)]
+pub enum TornCommit {
+    /// The segment ends within the commit header, i.e. the write intent
+    /// itself was not completed.
+    Header {
+        /// The byte position in the segment at which the commit starts.
+        byte_pos: u64,
+        /// The number of header bytes present, which is less than
+        /// [`commit::Header::LEN`].
+        len: u64,
+    },
+    /// The commit header was written, but the body is incomplete.
+    Body {
+        /// The byte position in the segment at which the commit starts.
+        byte_pos: u64,
+        /// The header of the commit.
+        header: commit::Header,
+        /// The number of bytes following the header, which is less than the
+        /// header's `len` plus [`Commit::CHECKSUM_LEN`].
+        body_len: u64,
+    },
+}
+
+impl TornCommit {
+    /// The byte position in the segment at which the torn commit starts.
+    pub fn byte_pos(&self) -> u64 {
+        match self {
+            Self::Header { byte_pos, .. } | Self::Body { byte_pos, .. } => *byte_pos,
+        }
+    }
+}
+
+/// Scan the commits in `segment` and return the torn commit at its end, if
+/// any.
+///
+/// `segment` must be positioned at the start of the segment, i.e. before the
+/// segment header. The scan ends at EOF, or when an all-zeroes commit header
+/// is encountered, in which case `None` is returned.
+///
+/// A commit is torn if the segment ends within its header, or before the
+/// records and checksum announced by its header, which is what a crash
+/// during [`write_commit_with_intent`] leaves behind. A complete commit with
+/// a wrong checksum is not torn, but corrupt, and causes an error of kind
+/// [`io::ErrorKind::InvalidData`] to be returned.
+pub async fn find_torn_commit<R>(mut segment: R) -> io::Result<Option<TornCommit>>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut buf = [0u8; segment::Header::LEN];
+    segment.read_exact(&mut buf).await?;
+    segment::Header::decode(&buf[..])?;
+
+    let mut byte_pos = segment::Header::LEN as u64;
+    let mut header_buf = Vec::with_capacity(commit::Header::LEN);
+    let mut body = Vec::new();
+    loop {
+        header_buf.clear();
+        (&mut segment)
+            .take(commit::Header::LEN as u64)
+            .read_to_end(&mut header_buf)
+            .await?;
+        if header_buf.is_empty() {
+            return Ok(None);
+        }
+        if header_buf.len() < commit::Header::LEN {
+            return Ok(Some(TornCommit::Header {
+                byte_pos,
+                len: header_buf.len() as u64,
+            }));
+        }
+        let Some(header) = commit::Header::decode(&header_buf[..])? else {
+            return Ok(None);
+        };
+
+        let expected_len = header.len as u64 + Commit::CHECKSUM_LEN as u64;
+        body.clear();
+        (&mut segment).take(expected_len).read_to_end(&mut body).await?;
+        if (body.len() as u64) < expected_len {
+            return Ok(Some(TornCommit::Body {
+                byte_pos,
+                header,
+                body_len: body.len() as u64,
+            }));
+        }
+
+        let (records, checksum) = body.split_at(header.len as usize);
+        let mut crc = Crc32cHasher::default();
+        crc.update(&header_buf);
+        crc.update(records);
+        if crc.checksum() != u32::from_le_bytes(checksum.try_into().unwrap()) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, ChecksumMismatch));
+        }
+
+        byte_pos += (commit::Header::LEN + body.len()) as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        pin::Pin,
+        sync::atomic::{AtomicBool, Ordering},
+        task::{Context, Poll},
+    };
+
+    use super::*;
+    use crate::{repo::mem, stream::IntoAsyncSegment as _};
+
+    /// A writer which fails all writes after the first fsync, simulating a
+    /// crash right after the write intent became durable.
+    struct CrashAfterFsync {
+        segment: mem::Segment,
+        synced: AtomicBool,
+    }
+
+    impl AsyncWrite for CrashAfterFsync {
+        fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+            if self.synced.load(Ordering::Relaxed) {
+                return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+            }
+            Pin::new(&mut self.segment).poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.segment).poll_flush(cx)
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.segment).poll_shutdown(cx)
+        }
+    }
+
+    impl AsyncFsync for CrashAfterFsync {
+        async fn fsync(&self) {
+            self.synced.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn commit(min_tx_offset: u64) -> Commit {
+        Commit {
+            min_tx_offset,
+            epoch: Commit::DEFAULT_EPOCH,
+            n: 3,
+            records: vec![min_tx_offset as u8; 64],
+        }
+    }
+
+    #[tokio::test]
+    async fn detects_commit_torn_after_intent() {
+        let segment = mem::Segment::default();
+        let mut writer = segment.clone();
+        segment::Header::default().write(&mut writer).unwrap();
+
+        let first = commit(0);
+        let crc = write_commit_with_intent(&mut writer, &first).await.unwrap();
+        assert_eq!(crc, first.write(&mut Vec::new()).unwrap());
+        assert!(find_torn_commit(segment.clone().into_async_reader())
+            .await
+            .unwrap()
+            .is_none());
+
+        let mut crashing = CrashAfterFsync {
+            segment: writer,
+            synced: AtomicBool::new(false),
+        };
+        let second = commit(3);
+        write_commit_with_intent(&mut crashing, &second).await.unwrap_err();
+
+        let torn = find_torn_commit(segment.clone().into_async_reader())
+            .await
+            .unwrap()
+            .expect("torn commit should be detected");
+        assert_eq!(
+            torn,
+            TornCommit::Body {
+                byte_pos: (segment::Header::LEN + first.encoded_len()) as u64,
+                header: commit::Header {
+                    min_tx_offset: 3,
+                    epoch: Commit::DEFAULT_EPOCH,
+                    n: 3,
+                    len: 64,
+                },
+                body_len: 0,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn detects_commit_torn_within_header() {
+        let mut buf = Vec::new();
+        segment::Header::default().write(&mut buf).unwrap();
+        commit(0).write(&mut buf).unwrap();
+        let byte_pos = buf.len() as u64;
+        commit(3).write(&mut buf).unwrap();
+        buf.truncate(byte_pos as usize + 10);
+
+        let torn = find_torn_commit(buf.as_slice()).await.unwrap();
+        assert_eq!(torn, Some(TornCommit::Header { byte_pos, len: 10 }));
+    }
+
+    #[tokio::test]
+    async fn corrupt_commit_is_not_torn() {
+        let mut buf = Vec::new();
+        segment::Header::default().write(&mut buf).unwrap();
+        commit(0).write(&mut buf).unwrap();
+        let last = buf.len() - 1;
+        buf[last] ^= 0xff;
+
+        let err = find_torn_commit(buf.as_slice()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
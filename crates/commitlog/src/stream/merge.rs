@@ -0,0 +1,114 @@
+use std::io;
+
+use async_stream::try_stream;
+use futures::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt as _};
+
+use crate::{segment, Commit};
+
+use super::{common::READ_BUDGET, decode_async_budgeted, dedup_by_offset};
+
+/// Merge the commits of several segments into a single stream, in ascending
+/// order of `min_tx_offset`.
+///
+/// Each of `readers` must be positioned at the start of a segment, i.e. before
+/// the segment header. The segments may be given in any order. The first
+/// commit of each segment is read, and the commit with the smallest
+/// `min_tx_offset` is yielded and replaced by the next commit of its segment,
+/// so at most one commit per segment is held in memory at a time. On equal
+/// offsets, the commit from the reader given first is yielded first.
+///
+/// Overlapping commits are dropped as per [`dedup_by_offset`], so segments
+/// with overlapping offset ranges, e.g. left behind by an interrupted
+/// compaction, result in a strictly monotonic stream. Gaps are not detected.
+pub fn merge_segments<R>(readers: Vec<R>) -> impl Stream<Item = io::Result<Commit>>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    let merged = try_stream! {
+        let mut heads = Vec::with_capacity(readers.len());
+        for mut reader in readers {
+            let mut buf = [0u8; segment::Header::LEN];
+            reader.read_exact(&mut buf).await?;
+            segment::Header::decode(&buf[..])?;
+            let head = decode_async_budgeted(&mut reader, READ_BUDGET).await?;
+            heads.push((reader, head));
+        }
+
+        loop {
+            let next = heads
+                .iter()
+                .enumerate()
+                .filter_map(|(i, (_, head))| head.as_ref().map(|commit| (commit.min_tx_offset, i)))
+                .min();
+            let Some((_, i)) = next else {
+                break;
+            };
+            let (reader, head) = &mut heads[i];
+            let commit = head.take().unwrap();
+            *head = decode_async_budgeted(reader, READ_BUDGET).await?;
+            yield commit;
+        }
+    };
+    dedup_by_offset(merged, |_| {})
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt as _;
+
+    use super::*;
+    use crate::{repo::mem, stream::IntoAsyncSegment as _, tests::helpers::DropFlag};
+
+    fn segment(offsets: impl IntoIterator<Item = u64>) -> mem::Segment {
+        let segment = mem::Segment::default();
+        let mut writer = segment.clone();
+        segment::Header::default().write(&mut writer).unwrap();
+        for min_tx_offset in offsets {
+            Commit {
+                min_tx_offset,
+                epoch: Commit::DEFAULT_EPOCH,
+                n: 2,
+                records: vec![min_tx_offset as u8; 16],
+            }
+            .write(&mut writer)
+            .unwrap();
+        }
+        segment
+    }
+
+    async fn merged_offsets(segments: Vec<mem::Segment>) -> Vec<u64> {
+        let readers = segments.into_iter().map(|s| s.into_async_reader()).collect();
+        merge_segments(readers)
+            .map_ok(|commit| commit.min_tx_offset)
+            .try_collect()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn merges_adjacent_segments() {
+        let offsets = merged_offsets(vec![segment([6, 8, 10]), segment([0, 2, 4])]).await;
+        assert_eq!(offsets, [0, 2, 4, 6, 8, 10]);
+    }
+
+    #[tokio::test]
+    async fn merges_overlapping_segments() {
+        let offsets = merged_offsets(vec![segment([0, 2, 4, 6]), segment([4, 6, 8]), segment([])]).await;
+        assert_eq!(offsets, [0, 2, 4, 6, 8]);
+    }
+
+    #[tokio::test]
+    async fn releases_segments_when_dropped_mid_iteration() {
+        let (readers, dropped): (Vec<_>, Vec<_>) = [segment([0, 2]), segment([4, 6])]
+            .into_iter()
+            .map(|segment| DropFlag::new(segment.into_async_reader()))
+            .unzip();
+        let mut merged = Box::pin(merge_segments(readers));
+        assert_eq!(merged.try_next().await.unwrap().unwrap().min_tx_offset, 0);
+        assert!(dropped.iter().all(|dropped| !dropped.is_set()));
+
+        drop(merged);
+        assert!(dropped.iter().all(|dropped| dropped.is_set()), "segments not released");
+    }
+}
@@ -0,0 +1,98 @@
+use std::io;
+
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt as _},
+    task::spawn_blocking,
+};
+
+use crate::{commit::Checksummer, Commit};
+
+/// Write `commit` to `out`, computing its checksum on the blocking thread pool
+/// if the commit is large.
+///
+/// Computing the checksum of a large commit is CPU-bound work which would
+/// otherwise stall the async runtime. If the records of `commit` exceed
+/// `offload_threshold` bytes, the commit is encoded into a buffer which is
+/// moved to [`spawn_blocking`] to compute the checksum. Smaller commits are
+/// checksummed inline, avoiding the overhead of spawning.
+///
+/// The written bytes are the same as those written by [`Commit::write`].
+/// Like it, returns the crc32 checksum of the commit on success. The commit is
+/// not flushed.
+pub async fn write_commit_async<W>(out: &mut W, commit: &Commit, offload_threshold: usize) -> io::Result<u32>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = Vec::with_capacity(commit.encoded_len());
+    if commit.records.len() <= offload_threshold {
+        let crc = commit.write(&mut buf)?;
+        out.write_all(&buf).await?;
+        return Ok(crc);
+    }
+
+    commit.write_with::<_, Deferred>(&mut buf)?;
+    buf.truncate(buf.len() - Commit::CHECKSUM_LEN);
+    let (mut buf, crc) = spawn_blocking(move || {
+        let crc = crc32c::crc32c(&buf);
+        (buf, crc)
+    })
+    .await
+    .map_err(io::Error::other)?;
+    buf.extend_from_slice(&crc.to_le_bytes());
+    out.write_all(&buf).await?;
+
+    Ok(crc)
+}
+
+/// A [`Checksummer`] which doesn't compute anything, used to encode a commit
+/// whose checksum is computed elsewhere.
+#[derive(Default)]
+struct Deferred;
+
+impl Checksummer for Deferred {
+    const ALGORITHM: u8 = Commit::CHECKSUM_ALGORITHM;
+
+    fn update(&mut self, _: &[u8]) {}
+
+    fn checksum(&self) -> u32 {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(len: usize) -> Commit {
+        Commit {
+            min_tx_offset: 0,
+            epoch: Commit::DEFAULT_EPOCH,
+            n: 1,
+            records: (0..len).map(|i| i as u8).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn offloads_large_commits() {
+        let commit = commit(4 * 1024 * 1024);
+        let mut out = Vec::new();
+        let crc = write_commit_async(&mut out, &commit, 1024).await.unwrap();
+
+        let mut expected = Vec::new();
+        assert_eq!(commit.write(&mut expected).unwrap(), crc);
+        assert!(out == expected);
+        assert_eq!(Commit::decode_exact(&out).unwrap(), commit);
+    }
+
+    #[test]
+    fn small_commits_dont_spawn() {
+        // Outside of a tokio runtime, `spawn_blocking` would panic.
+        let commit = commit(1024);
+        let mut out = Vec::new();
+        let crc = futures::executor::block_on(write_commit_async(&mut out, &commit, 1024)).unwrap();
+
+        let mut expected = Vec::new();
+        assert_eq!(commit.write(&mut expected).unwrap(), crc);
+        assert_eq!(out, expected);
+    }
+}
@@ -0,0 +1,105 @@
+use std::io;
+
+use async_stream::try_stream;
+use futures::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt as _};
+
+use crate::{segment, Commit};
+
+use super::{common::READ_BUDGET, decode_async_budgeted};
+
+/// Stream the commits in `segment`, along with the byte position in the
+/// segment at which each commit starts.
+///
+/// `segment` must be positioned at the start of the segment, i.e. before the
+/// segment header. The position of the first commit is thus
+/// [`segment::Header::LEN`], and each following position is the previous one
+/// plus the [`Commit::encoded_len`] of the previous commit. This allows to
+/// build an index of transaction offsets to byte positions while reading the
+/// segment, without a separate pass.
+///
+/// The stream ends at EOF, or when an all-zeroes commit header is
+/// encountered. Checksums are verified.
+pub fn commits_with_byte_pos<R>(mut segment: R) -> impl Stream<Item = io::Result<(u64, Commit)>>
+where
+    R: AsyncRead + Unpin,
+{
+    try_stream! {
+        let mut buf = [0u8; segment::Header::LEN];
+        segment.read_exact(&mut buf).await?;
+        segment::Header::decode(&buf[..])?;
+
+        let mut byte_pos = segment::Header::LEN as u64;
+        while let Some(commit) = decode_async_budgeted(&mut segment, READ_BUDGET).await? {
+            let len = commit.encoded_len() as u64;
+            yield (byte_pos, commit);
+            byte_pos += len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt as _;
+
+    use super::*;
+    use crate::{repo::mem, stream::IntoAsyncSegment as _, tests::helpers::DropFlag};
+
+    #[tokio::test]
+    async fn positions_are_running_sum_of_encoded_len() {
+        let commits = (0..5)
+            .map(|i| Commit {
+                min_tx_offset: i * 2,
+                epoch: Commit::DEFAULT_EPOCH,
+                n: 2,
+                records: vec![i as u8; 10 + 100 * i as usize],
+            })
+            .collect::<Vec<_>>();
+        let segment = mem::Segment::default();
+        let mut writer = segment.clone();
+        segment::Header::default().write(&mut writer).unwrap();
+        for commit in &commits {
+            commit.write(&mut writer).unwrap();
+        }
+
+        let read = commits_with_byte_pos(segment.into_async_reader())
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let mut expected_pos = segment::Header::LEN as u64;
+        for ((byte_pos, commit), expected) in read.iter().zip(&commits) {
+            assert_eq!(*byte_pos, expected_pos);
+            assert_eq!(commit, expected);
+            expected_pos += expected.encoded_len() as u64;
+        }
+        assert_eq!(read.len(), commits.len());
+        assert_eq!(expected_pos, writer.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn releases_segment_when_dropped_mid_iteration() {
+        let segment = mem::Segment::default();
+        let mut writer = segment.clone();
+        segment::Header::default().write(&mut writer).unwrap();
+        for i in 0..3 {
+            Commit {
+                min_tx_offset: i,
+                epoch: Commit::DEFAULT_EPOCH,
+                n: 1,
+                records: vec![i as u8; 16],
+            }
+            .write(&mut writer)
+            .unwrap();
+        }
+
+        let (reader, dropped) = DropFlag::new(segment.into_async_reader());
+        let mut stream = Box::pin(commits_with_byte_pos(reader));
+        let (byte_pos, _) = stream.try_next().await.unwrap().unwrap();
+        assert_eq!(byte_pos, segment::Header::LEN as u64);
+        assert!(!dropped.is_set());
+
+        drop(stream);
+        assert!(dropped.is_set(), "segment not released");
+    }
+}
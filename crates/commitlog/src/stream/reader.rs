@@ -40,6 +40,26 @@ use super::{
 /// If the commitlog is empty, that is does not contain any commits, the
 /// returned stream yields nothing.
 pub fn commits<R>(repo: R, range: impl RangeBounds<u64>) -> impl Stream<Item = io::Result<Bytes>>
+where
+    R: Repo + Send + 'static,
+    R::Segment: IntoAsyncSegment,
+{
+    commits_with_progress(repo, range, |_, _| {})
+}
+
+/// Like [`commits`], but reports progress to the `progress` callback.
+///
+/// The callback is invoked with the number of bytes and the number of
+/// commits yielded so far each time a commit is about to be yielded. The byte
+/// count includes segment headers.
+///
+/// The callback is owned by the stream, so the stream is [`Send`] as long as
+/// the callback is.
+pub fn commits_with_progress<R>(
+    repo: R,
+    range: impl RangeBounds<u64>,
+    mut progress: impl FnMut(u64, u64) + Send + 'static,
+) -> impl Stream<Item = io::Result<Bytes>>
 where
     R: Repo + Send + 'static,
     R::Segment: IntoAsyncSegment,
@@ -48,6 +68,8 @@ where
     let retain = move |segments: Vec<_>| retain_range(&segments, range);
     try_stream! {
         let segments = repo.existing_offsets().map(retain)?;
+        let mut bytes_read = 0;
+        let mut commits_read = 0;
         for segment_offset in segments {
             if range.start < segment_offset {
                 range.start = segment_offset;
@@ -63,18 +85,42 @@ where
             .into_async_reader();
 
             for await chunk in read_segment(repo.clone(), segment, segment_offset, range) {
-                yield chunk.inspect_err(|e| warn!("error reading segment {}: {}", segment_offset, e))?;
+                let chunk = chunk.inspect_err(|e| warn!("error reading segment {}: {}", segment_offset, e))?;
+                bytes_read += chunk.bytes().len() as u64;
+                let chunk = match chunk {
+                    Chunk::SegmentHeader(bytes) => bytes,
+                    Chunk::Commit(bytes) => {
+                        commits_read += 1;
+                        progress(bytes_read, commits_read);
+                        bytes
+                    }
+                };
+                yield chunk;
             }
         }
     }
 }
 
+/// The raw commitlog data yielded by [`read_segment`].
+enum Chunk {
+    SegmentHeader(Bytes),
+    Commit(Bytes),
+}
+
+impl Chunk {
+    fn bytes(&self) -> &Bytes {
+        match self {
+            Self::SegmentHeader(bytes) | Self::Commit(bytes) => bytes,
+        }
+    }
+}
+
 fn read_segment(
     repo: impl Repo + Send + 'static,
     mut segment: impl AsyncBufRead + AsyncSeek + Unpin + Send + 'static,
     segment_start: u64,
     range: RangeFromMaybeToInclusive,
-) -> impl Stream<Item = io::Result<Bytes>> {
+) -> impl Stream<Item = io::Result<Chunk>> {
     try_stream! {
         info!("reading segment {segment_start}");
         let (segment_header, segment_header_bytes) = {
@@ -133,11 +179,11 @@ fn read_segment(
                 // Send segment header if not sent already.
                 if let Some(header_bytes) = send_segment_header.take() {
                     trace!("sending segment header");
-                    yield header_bytes;
+                    yield Chunk::SegmentHeader(header_bytes);
                 }
 
                 trace!("sending commit {}", hdr.min_tx_offset);
-                yield commit_buf.as_buf().copy_to_bytes(commit_buf.filled_len());
+                yield Chunk::Commit(commit_buf.as_buf().copy_to_bytes(commit_buf.filled_len()));
             }
         }
     }
@@ -160,9 +206,55 @@ pub fn retain_range(offsets: &[u64], range: RangeFromMaybeToInclusive) -> Vec<u6
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use futures::TryStreamExt as _;
+
     use super::*;
+    use crate::{repo, stream::SegmentChainWriter, Commit};
     use proptest::prelude::*;
 
+    #[tokio::test]
+    async fn reports_progress_per_commit() {
+        let commits = (0..5)
+            .map(|i| Commit {
+                min_tx_offset: i,
+                epoch: Commit::DEFAULT_EPOCH,
+                n: 1,
+                records: vec![i as u8; 64],
+            })
+            .collect::<Vec<_>>();
+        let repo = repo::Memory::new();
+        // Three commits per segment.
+        let mut writer = SegmentChainWriter::new(repo.clone(), 3 * commits[0].encoded_len() as u64 + 16);
+        for commit in &commits {
+            writer.append(commit).await.unwrap();
+        }
+        writer.fsync_barrier().await.unwrap();
+        assert_eq!(repo.existing_offsets().unwrap().len(), 2);
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let stream = commits_with_progress(repo, .., {
+            let reports = reports.clone();
+            move |bytes, commits| reports.lock().unwrap().push((bytes, commits))
+        });
+        let chunks = stream.try_collect::<Vec<_>>().await.unwrap();
+
+        let commit_len = commits[0].encoded_len() as u64;
+        let header_len = segment::Header::LEN as u64;
+        let expected = (1..=5)
+            .map(|n| {
+                let headers = if n <= 3 { 1 } else { 2 };
+                (headers * header_len + n * commit_len, n)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(*reports.lock().unwrap(), expected);
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.len() as u64).sum::<u64>(),
+            expected[4].0
+        );
+    }
+
     fn retain_range(offsets: &[u64], range: impl RangeBounds<u64>) -> Vec<u64> {
         super::retain_range(offsets, RangeFromMaybeToInclusive::from_range_bounds(range))
     }
@@ -0,0 +1,194 @@
+use std::io::{self, SeekFrom};
+
+use async_stream::try_stream;
+use futures::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncSeek, AsyncSeekExt as _};
+
+use crate::{payload::Decoder, segment, Transaction};
+
+use super::{common::READ_BUDGET, decode_async_budgeted};
+
+/// The position of a replay of a segment, from which the replay can be
+/// resumed via [`resume_replay`].
+///
+/// Yielded by [`replay_with_cursor`] and [`resume_replay`] after each
+/// transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplayCursor {
+    /// The byte position in the segment of the commit containing the next
+    /// transaction.
+    pub byte_pos: u64,
+    /// The offset of the next transaction to apply.
+    pub next_offset: u64,
+}
+
+/// Stream the transactions in `segment`, each along with the [`ReplayCursor`]
+/// pointing just past it.
+///
+/// `segment` must be positioned at the start of the segment, i.e. before the
+/// segment header. The records are decoded using `de`, passing the log format
+/// version from the segment header.
+///
+/// Persisting the cursor of the last transaction applied allows to continue an
+/// interrupted replay via [`resume_replay`], e.g. after a restart.
+pub fn replay_with_cursor<R, D>(
+    segment: R,
+    de: D,
+) -> impl Stream<Item = Result<(Transaction<D::Record>, ReplayCursor), D::Error>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+    D: Decoder,
+    D::Error: From<io::Error>,
+{
+    replay(segment, None, de)
+}
+
+/// Like [`replay_with_cursor`], but resume the replay at `cursor`.
+///
+/// The commit at [`ReplayCursor::byte_pos`] is read, and its transactions
+/// before [`ReplayCursor::next_offset`] are skipped, so no transaction is
+/// yielded twice, and none is missed.
+///
+/// `segment` must be the same segment the cursor was obtained from.
+pub fn resume_replay<R, D>(
+    segment: R,
+    cursor: ReplayCursor,
+    de: D,
+) -> impl Stream<Item = Result<(Transaction<D::Record>, ReplayCursor), D::Error>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+    D: Decoder,
+    D::Error: From<io::Error>,
+{
+    replay(segment, Some(cursor), de)
+}
+
+fn replay<R, D>(
+    mut segment: R,
+    cursor: Option<ReplayCursor>,
+    de: D,
+) -> impl Stream<Item = Result<(Transaction<D::Record>, ReplayCursor), D::Error>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+    D: Decoder,
+    D::Error: From<io::Error>,
+{
+    try_stream! {
+        let mut buf = [0u8; segment::Header::LEN];
+        segment.read_exact(&mut buf).await?;
+        let version = segment::Header::decode(&buf[..])?.log_format_version;
+
+        let ReplayCursor { mut byte_pos, mut next_offset } = cursor.unwrap_or(ReplayCursor {
+            byte_pos: segment::Header::LEN as u64,
+            next_offset: 0,
+        });
+        if byte_pos != segment::Header::LEN as u64 {
+            segment.seek(SeekFrom::Start(byte_pos)).await?;
+        }
+
+        while let Some(commit) = decode_async_budgeted(&mut segment, READ_BUDGET).await? {
+            let commit_len = commit.encoded_len() as u64;
+            let end_offset = commit.tx_range().end;
+            for tx in commit.into_transactions_owned(version, next_offset, &de) {
+                let tx = tx?;
+                next_offset = tx.offset + 1;
+                // After the last transaction of a commit, point to the next
+                // commit, so resuming doesn't need to re-read this one.
+                let cursor = ReplayCursor {
+                    byte_pos: if next_offset == end_offset { byte_pos + commit_len } else { byte_pos },
+                    next_offset,
+                };
+                yield (tx, cursor);
+            }
+            byte_pos += commit_len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{StreamExt as _, TryStreamExt as _};
+
+    use super::*;
+    use crate::{payload::ArrayDecoder, repo::mem, stream::IntoAsyncSegment as _, tests::helpers::DropFlag, Commit};
+
+    #[tokio::test]
+    async fn resume_from_cursor() {
+        let segment = mem::Segment::default();
+        let mut writer = segment.clone();
+        segment::Header::default().write(&mut writer).unwrap();
+        for i in 0..4u64 {
+            let records = (0..3).flat_map(|j| [(i * 3 + j) as u8; 4]).collect();
+            Commit {
+                min_tx_offset: i * 3,
+                epoch: Commit::DEFAULT_EPOCH,
+                n: 3,
+                records,
+            }
+            .write(&mut writer)
+            .unwrap();
+        }
+
+        // Replay into the middle of the second commit.
+        let head = replay_with_cursor(segment.clone().into_async_reader(), ArrayDecoder::<4>)
+            .take(5)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let (_, cursor) = head.last().unwrap();
+        assert_eq!(cursor.next_offset, 5);
+
+        let tail = resume_replay(segment.clone().into_async_reader(), *cursor, ArrayDecoder::<4>)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let replayed = head.iter().chain(&tail).map(|(tx, _)| tx).collect::<Vec<_>>();
+        assert_eq!(
+            replayed.iter().map(|tx| tx.offset).collect::<Vec<_>>(),
+            (0..12).collect::<Vec<_>>()
+        );
+        assert!(replayed.iter().all(|tx| tx.txdata == [tx.offset as u8; 4]));
+
+        // A cursor at a commit boundary points to the start of the next commit.
+        let (_, boundary) = head[2];
+        assert_eq!(boundary.next_offset, 3);
+        assert_eq!(
+            boundary.byte_pos,
+            (segment::Header::LEN + Commit::FRAMING_LEN + 12) as u64
+        );
+        let (_, last) = tail.last().unwrap();
+        assert_eq!(last.byte_pos, writer.len() as u64);
+        let rest = resume_replay(segment.into_async_reader(), *last, ArrayDecoder::<4>)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[tokio::test]
+    async fn releases_segment_when_dropped_mid_iteration() {
+        let segment = mem::Segment::default();
+        let mut writer = segment.clone();
+        segment::Header::default().write(&mut writer).unwrap();
+        for i in 0..3u64 {
+            Commit {
+                min_tx_offset: i,
+                epoch: Commit::DEFAULT_EPOCH,
+                n: 1,
+                records: vec![i as u8; 4],
+            }
+            .write(&mut writer)
+            .unwrap();
+        }
+
+        let (reader, dropped) = DropFlag::new(segment.into_async_reader());
+        let mut replay = Box::pin(replay_with_cursor(reader, ArrayDecoder::<4>));
+        let (tx, _) = replay.try_next().await.unwrap().unwrap();
+        assert_eq!(tx.offset, 0);
+        assert!(!dropped.is_set());
+
+        drop(replay);
+        assert!(dropped.is_set(), "segment not released");
+    }
+}
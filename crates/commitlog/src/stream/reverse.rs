@@ -0,0 +1,187 @@
+use std::io::{self, SeekFrom};
+
+use async_stream::try_stream;
+use futures::Stream;
+use log::trace;
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncSeek, AsyncSeekExt as _};
+
+use crate::{commit, repo::TxOffsetIndex, segment, Commit};
+
+use super::common::{read_exact, CommitBuf};
+
+/// Stream the commits in `segment` newest-first.
+///
+/// The offset index `index` of the segment is used to divide the segment into
+/// chunks, which are visited in reverse order. Each chunk is decoded front to
+/// back and its commits are yielded in reverse, so only the commits of one
+/// chunk are held in memory at a time.
+///
+/// The entries of `index` are snapshotted when this function is called, and
+/// the length of `segment` when the stream is first polled. Commits appended
+/// after that are not yielded, so the segment may safely be written to
+/// concurrently. An index entry which doesn't point to a commit boundary
+/// results in an error of kind [`io::ErrorKind::InvalidData`].
+///
+/// Like [`Commit::decode`], the checksum of each commit is verified.
+///
+/// The stream owns `segment`, which is dropped as soon as the stream is,
+/// even if it was not consumed to the end.
+pub fn commits_reverse<R>(mut segment: R, index: &TxOffsetIndex) -> io::Result<impl Stream<Item = io::Result<Commit>>>
+where
+    R: AsyncRead + AsyncSeek + Unpin + Send + 'static,
+{
+    let mut boundaries = vec![segment::Header::LEN as u64];
+    for entry in index {
+        let (_, byte_offset) = entry.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        boundaries.push(byte_offset);
+    }
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    Ok(try_stream! {
+        let end = segment.seek(SeekFrom::End(0)).await?;
+        trace!("reverse reading segment of {} bytes in {} chunks", end, boundaries.len());
+
+        let mut commit_buf = CommitBuf::default();
+        let mut chunk_end = end;
+        for &chunk_start in boundaries.iter().rev() {
+            if chunk_start >= chunk_end {
+                continue;
+            }
+            let is_last = chunk_end == end;
+            let commits = read_chunk(&mut segment, &mut commit_buf, chunk_start, chunk_end, is_last).await?;
+            for commit in commits.into_iter().rev() {
+                yield commit;
+            }
+            chunk_end = chunk_start;
+        }
+    })
+}
+
+/// Decode the commits from `start` up to, but excluding, `end`.
+///
+/// If `is_last`, `end` is the end of the segment, and a trailing commit which
+/// extends beyond it is ignored. Otherwise, `end` must be a commit boundary.
+async fn read_chunk<R>(
+    segment: &mut R,
+    commit_buf: &mut CommitBuf,
+    start: u64,
+    end: u64,
+    is_last: bool,
+) -> io::Result<Vec<Commit>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    trace!("reading chunk {start}..{end}");
+    let mut commits = Vec::new();
+    let mut pos = segment.seek(SeekFrom::Start(start)).await?;
+    while pos < end {
+        if read_exact(segment, &mut commit_buf.header).await?.is_eof() {
+            break;
+        }
+        let Some(hdr) = commit::Header::decode(&commit_buf.header[..])? else {
+            break;
+        };
+        let commit_len = (commit::Header::LEN + hdr.len as usize + Commit::CHECKSUM_LEN) as u64;
+        if pos + commit_len > end {
+            // The commit was not fully written when `end` was determined.
+            break;
+        }
+        commit_buf.body.resize(hdr.len as usize + Commit::CHECKSUM_LEN, 0);
+        segment.read_exact(&mut commit_buf.body).await?;
+        let commit = Commit::decode(commit_buf.as_reader())?
+            .expect("commit decode cannot return `None` because we already decoded the header");
+        commits.push(commit);
+        pos += commit_len;
+    }
+    if pos != end && !is_last {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("index entry at byte offset {end} is not at a commit boundary"),
+        ));
+    }
+
+    Ok(commits)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt as _;
+    use spacetimedb_paths::{server::CommitLogDir, FromPathUnchecked as _};
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{
+        repo::TxOffsetIndexMut,
+        tests::helpers::{enable_logging, DropFlag},
+    };
+
+    #[tokio::test]
+    async fn yields_newest_first() {
+        enable_logging();
+
+        let commits = (0..3)
+            .map(|i| Commit {
+                min_tx_offset: i * 2,
+                epoch: Commit::DEFAULT_EPOCH,
+                n: 2,
+                records: vec![i as u8; 64],
+            })
+            .collect::<Vec<_>>();
+        let mut buf = Vec::new();
+        segment::Header::default().write(&mut buf).unwrap();
+        let mut byte_offsets = Vec::new();
+        for commit in &commits {
+            byte_offsets.push(buf.len() as u64);
+            commit.write(&mut buf).unwrap();
+        }
+
+        // Index only the second commit.
+        let tmp = tempdir().unwrap();
+        let index_path = CommitLogDir::from_path_unchecked(tmp.path()).index(0);
+        let mut index = TxOffsetIndexMut::create_index_file(&index_path, 10).unwrap();
+        index.append(commits[1].min_tx_offset, byte_offsets[1]).unwrap();
+        let index = TxOffsetIndex::from(index);
+
+        let stream = commits_reverse(io::Cursor::new(buf.clone()), &index).unwrap();
+        let reversed = stream.try_collect::<Vec<_>>().await.unwrap();
+        assert_eq!(reversed, commits.iter().rev().cloned().collect::<Vec<_>>());
+
+        // A partially written commit at the end is ignored.
+        let start = byte_offsets[2] as usize;
+        buf.extend_from_within(start..start + 10);
+        let stream = commits_reverse(io::Cursor::new(buf), &index).unwrap();
+        let reversed = stream.try_collect::<Vec<_>>().await.unwrap();
+        assert_eq!(reversed.len(), commits.len());
+    }
+
+    #[tokio::test]
+    async fn releases_segment_when_dropped_mid_iteration() {
+        enable_logging();
+
+        let mut buf = Vec::new();
+        segment::Header::default().write(&mut buf).unwrap();
+        for i in 0..3 {
+            Commit {
+                min_tx_offset: i,
+                epoch: Commit::DEFAULT_EPOCH,
+                n: 1,
+                records: vec![i as u8; 16],
+            }
+            .write(&mut buf)
+            .unwrap();
+        }
+        let tmp = tempdir().unwrap();
+        let index_path = CommitLogDir::from_path_unchecked(tmp.path()).index(0);
+        let index = TxOffsetIndex::from(TxOffsetIndexMut::create_index_file(&index_path, 10).unwrap());
+
+        let (segment, dropped) = DropFlag::new(io::Cursor::new(buf));
+        let mut stream = Box::pin(commits_reverse(segment, &index).unwrap());
+        let newest = stream.try_next().await.unwrap().unwrap();
+        assert_eq!(newest.min_tx_offset, 2);
+        assert!(!dropped.is_set());
+
+        drop(stream);
+        assert!(dropped.is_set(), "segment not released");
+    }
+}
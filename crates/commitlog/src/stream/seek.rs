@@ -0,0 +1,76 @@
+use std::io::{self, SeekFrom};
+
+use log::trace;
+use tokio::io::{AsyncRead, AsyncSeek, AsyncSeekExt as _};
+
+use crate::{commit, Commit};
+
+use super::common::{read_exact, CommitBuf};
+
+/// Advance `reader` to the first commit containing the transaction `offset`.
+///
+/// `reader` must be positioned at a commit boundary, e.g. right after the
+/// segment header. Only the commit headers are decoded, the bodies of commits
+/// preceding `offset` are skipped over without reading them.
+///
+/// Returns `reader` positioned at the start of the first commit whose
+/// [`Commit::tx_range`] ends after `offset`. That is, the commit containing
+/// `offset`, or the next commit if there is a gap in the log.
+///
+/// Returns `None` if EOF or an all-zeroes commit header is encountered before
+/// such a commit is found.
+pub async fn seek_to_offset<R>(mut reader: R, offset: u64) -> io::Result<Option<R>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let mut commit_buf = CommitBuf::default();
+    loop {
+        if read_exact(&mut reader, &mut commit_buf.header).await?.is_eof() {
+            trace!("eof reading commit header");
+            return Ok(None);
+        }
+        let Some(hdr) = commit::Header::decode(&commit_buf.header[..])? else {
+            trace!("all-zeroes commit header");
+            return Ok(None);
+        };
+        if hdr.min_tx_offset + hdr.n as u64 > offset {
+            trace!("found commit {} containing offset {}", hdr.min_tx_offset, offset);
+            reader.seek(SeekFrom::Current(-(commit::Header::LEN as i64))).await?;
+            return Ok(Some(reader));
+        }
+        reader
+            .seek(SeekFrom::Current(hdr.len as i64 + Commit::CHECKSUM_LEN as i64))
+            .await?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::helpers::enable_logging;
+
+    #[tokio::test]
+    async fn lands_on_containing_commit() {
+        enable_logging();
+
+        let commits = (0..4)
+            .map(|i| Commit {
+                min_tx_offset: i * 3,
+                epoch: Commit::DEFAULT_EPOCH,
+                n: 3,
+                records: vec![i as u8; 32],
+            })
+            .collect::<Vec<_>>();
+        let mut buf = Vec::new();
+        for commit in &commits {
+            commit.write(&mut buf).unwrap();
+        }
+
+        for (offset, expected) in [(0, 0), (2, 0), (3, 1), (7, 2), (11, 3)] {
+            let mut reader = seek_to_offset(io::Cursor::new(&buf), offset).await.unwrap().unwrap();
+            let commit = Commit::decode(&mut reader).unwrap().unwrap();
+            assert_eq!(commit, commits[expected], "offset {offset}");
+        }
+        assert!(seek_to_offset(io::Cursor::new(&buf), 12).await.unwrap().is_none());
+    }
+}
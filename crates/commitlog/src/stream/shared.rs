@@ -0,0 +1,203 @@
+use std::{
+    future::Future as _,
+    io::{self, SeekFrom},
+    mem,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncSeek, ReadBuf},
+    task::{spawn_blocking, JoinHandle},
+};
+
+/// Positioned reads, which don't depend on or modify a cursor.
+///
+/// This allows several readers to share a single handle to a segment.
+pub trait ReadAt: Send + Sync + 'static {
+    /// Read up to `buf.len()` bytes starting at byte `offset`.
+    ///
+    /// Returns the number of bytes read, which is zero at the end of the
+    /// segment.
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize>;
+    /// The current size of the segment in bytes.
+    fn size(&self) -> io::Result<u64>;
+}
+
+impl ReadAt for std::fs::File {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        #[cfg(unix)]
+        return std::os::unix::fs::FileExt::read_at(self, buf, offset);
+        // NOTE: Moves the file cursor, which shared readers don't rely on.
+        #[cfg(windows)]
+        return std::os::windows::fs::FileExt::seek_read(self, buf, offset);
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        self.metadata().map(|meta| meta.len())
+    }
+}
+
+/// A segment handle from which any number of independent readers can be
+/// created, see [`super::IntoAsyncSegment::into_shared`].
+///
+/// Cloning is cheap, all clones refer to the same handle.
+pub struct SharedSegment<S>(Arc<S>);
+
+impl<S> Clone for SharedSegment<S> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<S: ReadAt> SharedSegment<S> {
+    pub fn new(segment: S) -> Self {
+        Self(Arc::new(segment))
+    }
+
+    /// Create a new reader positioned at the start of the segment.
+    ///
+    /// The reader has its own position, so readers can be moved to different
+    /// tasks and read different parts of the segment concurrently. Reads are
+    /// performed on the blocking thread pool.
+    ///
+    /// The reader is not buffered, wrapping it in a [`tokio::io::BufReader`]
+    /// is recommended.
+    pub fn reader(&self) -> SharedSegmentReader<S> {
+        SharedSegmentReader {
+            segment: self.0.clone(),
+            pos: 0,
+            state: State::Idle(Vec::new()),
+        }
+    }
+}
+
+/// A reader created via [`SharedSegment::reader`].
+pub struct SharedSegmentReader<S> {
+    segment: Arc<S>,
+    pos: u64,
+    state: State,
+}
+
+enum State {
+    /// No read is in progress. Holds the buffer to read into.
+    Idle(Vec<u8>),
+    /// A read at the current position is in progress.
+    Busy(JoinHandle<(Vec<u8>, io::Result<usize>)>),
+}
+
+/// The maximum number of bytes read by a single blocking read.
+const MAX_READ: usize = 64 * 1024;
+
+impl<S: ReadAt> AsyncRead for SharedSegmentReader<S> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        loop {
+            match &mut this.state {
+                State::Idle(tmp) => {
+                    let mut tmp = mem::take(tmp);
+                    tmp.resize(buf.remaining().min(MAX_READ), 0);
+                    let segment = this.segment.clone();
+                    let pos = this.pos;
+                    this.state = State::Busy(spawn_blocking(move || {
+                        let res = segment.read_at(&mut tmp, pos);
+                        (tmp, res)
+                    }));
+                }
+                State::Busy(handle) => {
+                    let res = ready!(Pin::new(handle).poll(cx));
+                    this.state = State::Idle(Vec::new());
+                    let (tmp, res) = res.map_err(io::Error::other)?;
+                    // `buf` may be smaller than the one the read was started
+                    // with, if a previous read was cancelled.
+                    let n = res?.min(buf.remaining());
+                    buf.put_slice(&tmp[..n]);
+                    this.pos += n as u64;
+                    this.state = State::Idle(tmp);
+
+                    return Poll::Ready(Ok(()));
+                }
+            }
+        }
+    }
+}
+
+impl<S: ReadAt> AsyncSeek for SharedSegmentReader<S> {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+        let (base, offset) = match position {
+            SeekFrom::Start(n) => (n, 0),
+            SeekFrom::End(n) => (this.segment.size()?, n),
+            SeekFrom::Current(n) => (this.pos, n),
+        };
+        this.pos = base.checked_add_signed(offset).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        // A read in progress is at the old position, its result is discarded.
+        if let State::Busy(_) = this.state {
+            this.state = State::Idle(Vec::new());
+        }
+
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncSeekExt as _, BufReader};
+
+    use super::*;
+    use crate::{
+        repo::mem,
+        segment,
+        stream::{decode_async_budgeted, IntoAsyncSegment as _},
+        Commit,
+    };
+
+    #[tokio::test]
+    async fn concurrent_readers_over_one_segment() {
+        let commits = (0..6)
+            .map(|i| Commit {
+                min_tx_offset: i,
+                epoch: Commit::DEFAULT_EPOCH,
+                n: 1,
+                records: vec![i as u8; 100_000],
+            })
+            .collect::<Vec<_>>();
+        let segment = mem::Segment::default();
+        let mut writer = segment.clone();
+        segment::Header::default().write(&mut writer).unwrap();
+        for commit in &commits {
+            commit.write(&mut writer).unwrap();
+        }
+
+        let shared = segment.into_shared();
+        let read = |start: usize, count: usize| {
+            let mut reader = BufReader::new(shared.reader());
+            let offset = segment::Header::LEN + start * commits[0].encoded_len();
+            tokio::spawn(async move {
+                reader.seek(SeekFrom::Start(offset as u64)).await.unwrap();
+                let mut decoded = Vec::new();
+                for _ in 0..count {
+                    decoded.push(decode_async_budgeted(&mut reader, 4096).await.unwrap().unwrap());
+                }
+                decoded
+            })
+        };
+        let (front, back) = tokio::join!(read(0, 3), read(3, 3));
+
+        assert_eq!(front.unwrap(), &commits[..3]);
+        assert_eq!(back.unwrap(), &commits[3..]);
+    }
+}
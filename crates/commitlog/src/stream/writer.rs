@@ -102,6 +102,7 @@ where
             tx_range,
             size_in_bytes: _,
             max_epoch: _,
+            checksum: _,
         } = segment::Metadata::extract(last, &mut segment).or_else(|e| match e {
             error::SegmentMetadata::InvalidCommit { sofar, source } => match on_trailing {
                 OnTrailingData::Error => Err(io::Error::new(io::ErrorKind::InvalidData, source)),
@@ -1,6 +1,18 @@
 use std::fmt::Debug;
+#[cfg(feature = "streaming")]
+use std::{
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
 
 use env_logger::Env;
+#[cfg(feature = "streaming")]
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
 
 use crate::{
     commitlog,
@@ -54,6 +66,63 @@ where
     }
 }
 
+/// A reader which records when it is dropped, e.g. to check that a stream
+/// releases its segment when dropped mid-iteration.
+#[cfg(feature = "streaming")]
+pub struct DropFlag<R> {
+    inner: R,
+    dropped: Arc<AtomicBool>,
+}
+
+#[cfg(feature = "streaming")]
+impl<R> DropFlag<R> {
+    /// Wrap `inner`, returning the handle to check if it was dropped.
+    pub fn new(inner: R) -> (Self, Dropped) {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let this = Self {
+            inner,
+            dropped: dropped.clone(),
+        };
+        (this, Dropped(dropped))
+    }
+}
+
+#[cfg(feature = "streaming")]
+impl<R> Drop for DropFlag<R> {
+    fn drop(&mut self) {
+        self.dropped.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "streaming")]
+impl<R: AsyncRead + Unpin> AsyncRead for DropFlag<R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+#[cfg(feature = "streaming")]
+impl<R: AsyncSeek + Unpin> AsyncSeek for DropFlag<R> {
+    fn start_seek(mut self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        Pin::new(&mut self.inner).start_seek(position)
+    }
+
+    fn poll_complete(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.inner).poll_complete(cx)
+    }
+}
+
+/// Whether a [`DropFlag`] was dropped.
+#[cfg(feature = "streaming")]
+pub struct Dropped(Arc<AtomicBool>);
+
+#[cfg(feature = "streaming")]
+impl Dropped {
+    pub fn is_set(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
 pub fn enable_logging() {
     let _ = env_logger::Builder::from_env(Env::default().default_filter_or("trace"))
         .format_timestamp(None)
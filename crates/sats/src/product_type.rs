@@ -15,6 +15,8 @@ pub const CONNECTION_ID_TAG: &str = "__connection_id__";
 pub const TIMESTAMP_TAG: &str = "__timestamp_micros_since_unix_epoch__";
 /// The tag used inside the special `TimeDuration` product type.
 pub const TIME_DURATION_TAG: &str = "__time_duration_micros__";
+/// The tag used for `u32` fields holding a Unicode scalar value.
+pub const CHAR_TAG: &str = "__char__";
 
 /// A structural product type  of the factors given by `elements`.
 ///
@@ -122,9 +124,16 @@ impl ProductType {
     }
 
     /// Returns whether this is a special known `tag`,
-    /// currently `Address`, `Identity`, `Timestamp` or `TimeDuration`.
+    /// currently `Address`, `Identity`, `Timestamp`, `TimeDuration` or `char`.
     pub fn is_special_tag(tag_name: &str) -> bool {
-        [IDENTITY_TAG, CONNECTION_ID_TAG, TIMESTAMP_TAG, TIME_DURATION_TAG].contains(&tag_name)
+        [
+            IDENTITY_TAG,
+            CONNECTION_ID_TAG,
+            TIMESTAMP_TAG,
+            TIME_DURATION_TAG,
+            CHAR_TAG,
+        ]
+        .contains(&tag_name)
     }
 
     /// Returns whether this is a special known type, currently `ConnectionId` or `Identity`.
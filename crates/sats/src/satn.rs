@@ -1,27 +1,73 @@
 use crate::{
     algebraic_value::ser::ValueSerializer,
+    product_type::CHAR_TAG,
     ser::{self, Serialize},
-    ProductType,
+    sum_type::{OPTION_NONE_TAG, OPTION_SOME_TAG},
+    AlgebraicValue, ProductType, ProductValue, WithTypespace,
 };
 use crate::{i256, u256};
+use core::cell::{Cell, RefCell};
+use core::convert::Infallible;
 use core::fmt;
 use core::fmt::Write as _;
+use core::iter;
+use core::mem;
 use derive_more::{From, Into};
+use std::borrow::Cow;
+use std::io;
 
 /// An extension trait for [`Serialize`] providing formatting methods.
 pub trait Satn: ser::Serialize {
     /// Formats the value using the SATN data format into the formatter `f`.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Writer::with(f, |f| self.serialize(SatnFormatter { f }))?;
+        self.fmt_with(f, &Options::default())
+    }
+
+    /// Formats the value using the SATN data format into the formatter `f`,
+    /// as configured by `opts`.
+    ///
+    /// If `f` has a width, scalar values are padded according to its width,
+    /// fill and alignment, left-aligned by default.
+    /// Arrays, products and sums ignore these flags.
+    /// Unlike [`fmt::Formatter::pad`], the precision is ignored,
+    /// so values are never truncated.
+    fn fmt_with(&self, f: &mut fmt::Formatter, opts: &Options) -> fmt::Result {
+        // We use `alternate`, i.e., the `#` flag to let the user trigger pretty printing.
+        let pretty = f.alternate();
+        let cx = &Context::new(*opts);
+        if f.width().is_some() {
+            // Whether the value is a scalar is only known after rendering it.
+            let mut out = String::new();
+            Writer::with(&mut out, pretty, opts, |f| self.serialize(SatnFormatter { f, cx }))?;
+            if cx.nested.get() {
+                f.write_str(&out)?;
+            } else {
+                pad(f, &out)?;
+            }
+        } else {
+            Writer::with(f, pretty, opts, |f| self.serialize(SatnFormatter { f, cx }))?;
+        }
+        if opts.trailing_newline {
+            f.write_str(opts.newline)?;
+        }
         Ok(())
     }
 
     /// Formats the value using the postgres SATN(SatnFormatter { f }, /* AlgebraicType */) formatter `f`.
     fn fmt_psql(&self, f: &mut fmt::Formatter, ty: &ProductType) -> fmt::Result {
-        Writer::with(f, |f| {
+        self.fmt_psql_with(f, ty, &Options::default())
+    }
+
+    /// Formats the value using the postgres SATN formatter `f`,
+    /// as configured by `opts`.
+    fn fmt_psql_with(&self, f: &mut fmt::Formatter, ty: &ProductType, opts: &Options) -> fmt::Result {
+        let pretty = f.alternate();
+        let cx = &Context::new(*opts);
+        Writer::with(f, pretty, &cx.opts, |f| {
             self.serialize(PsqlFormatter {
-                fmt: SatnFormatter { f },
+                fmt: SatnFormatter { f, cx },
                 ty,
+                is_char: false,
             })
         })?;
         Ok(())
@@ -36,329 +82,2568 @@ pub trait Satn: ser::Serialize {
     fn to_satn_pretty(&self) -> String {
         format!("{:#}", Wrapper::from_ref(self))
     }
-}
 
-impl<T: ser::Serialize + ?Sized> Satn for T {}
-
-/// A wrapper around a `T: Satn`
-/// providing `Display` and `Debug` implementations
-/// that uses the SATN formatting for `T`.
-#[repr(transparent)]
-pub struct Wrapper<T: ?Sized>(pub T);
+    /// Formats the value using the SATN data format into the returned `String`,
+    /// as configured by `opts`.
+    fn to_satn_with(&self, opts: &Options) -> String {
+        render(self, false, &Context::new(*opts))
+    }
 
-impl<T: ?Sized> Wrapper<T> {
-    /// Converts `&T` to `&Wrapper<T>`.
-    pub fn from_ref(t: &T) -> &Self {
-        // SAFETY: `repr(transparent)` turns the ABI of `T`
-        // into the same as `Self` so we can also cast `&T` to `&Self`.
-        unsafe { &*(t as *const T as *const Self) }
+    /// Pretty prints the value using the SATN data format into the returned `String`,
+    /// as configured by `opts`.
+    fn to_satn_pretty_with(&self, opts: &Options) -> String {
+        render(self, true, &Context::new(*opts))
     }
-}
 
-impl<T: Satn + ?Sized> fmt::Display for Wrapper<T> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0.fmt(f)
+    /// Formats the value like [`Satn::to_satn`], prefixed with the length
+    /// of the rendering in bytes as a 4-byte little-endian integer,
+    /// for sending over a length-prefixed protocol.
+    ///
+    /// Panics if the rendering exceeds `u32::MAX` bytes.
+    fn to_satn_framed(&self) -> Vec<u8> {
+        let body = self.to_satn();
+        let len = u32::try_from(body.len()).expect("SATN rendering should not exceed `u32::MAX` bytes");
+        let mut out = Vec::with_capacity(4 + body.len());
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(body.as_bytes());
+        out
     }
-}
 
-impl<T: Satn + ?Sized> fmt::Debug for Wrapper<T> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0.fmt(f)
+    /// Computes the dimensions of the pretty printed SATN rendering of the value,
+    /// without allocating the rendering.
+    fn pretty_metrics(&self) -> PrettyMetrics {
+        let mut metrics = MetricsWriter::default();
+        let cx = &Context::new(Options::default());
+        Writer::with(&mut metrics, true, &cx.opts, |f| {
+            self.serialize(SatnFormatter { f, cx })
+        })
+        .expect("`MetricsWriter` should never fail");
+        metrics.finish()
     }
 }
 
-/// A wrapper around a `T: Satn`
-/// providing `Display` and `Debug` implementations
-/// that uses postgres SATN formatting for `T`.
-pub struct PsqlWrapper<'a, T: ?Sized> {
-    pub ty: &'a ProductType,
-    pub value: T,
-}
+impl<T: ser::Serialize + ?Sized> Satn for T {}
 
-impl<T: ?Sized> PsqlWrapper<'_, T> {
-    /// Converts `&T` to `&PsqlWrapper<T>`.
-    pub fn from_ref(t: &T) -> &Self {
-        // SAFETY: `repr(transparent)` turns the ABI of `T`
-        // into the same as `Self` so we can also cast `&T` to `&Self`.
-        unsafe { &*(t as *const T as *const Self) }
+/// Writes `s` to `f`, padded according to the width, fill and alignment of `f`.
+///
+/// Unlike [`fmt::Formatter::pad`], this ignores the precision of `f`,
+/// which would otherwise truncate `s`.
+fn pad(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    let padding = f.width().unwrap_or(0).saturating_sub(s.chars().count());
+    let (pre, post) = match f.align() {
+        None | Some(fmt::Alignment::Left) => (0, padding),
+        Some(fmt::Alignment::Right) => (padding, 0),
+        Some(fmt::Alignment::Center) => (padding / 2, padding - padding / 2),
+    };
+    let fill = f.fill();
+    for _ in 0..pre {
+        f.write_char(fill)?;
     }
+    f.write_str(s)?;
+    for _ in 0..post {
+        f.write_char(fill)?;
+    }
+    Ok(())
 }
 
-impl<T: Satn + ?Sized> fmt::Display for PsqlWrapper<'_, T> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.value.fmt_psql(f, self.ty)
+/// Formats `value` into a `String` using the context `cx`,
+/// pretty printing if `pretty` is set.
+fn render<T: ser::Serialize + ?Sized>(value: &T, pretty: bool, cx: &Context) -> String {
+    let mut out = String::new();
+    Writer::with(&mut out, pretty, &cx.opts, |f| value.serialize(SatnFormatter { f, cx }))
+        .expect("writing to a `String` should never fail");
+    if cx.opts.trailing_newline {
+        out.push_str(cx.opts.newline);
     }
+    out
 }
 
-impl<T: Satn + ?Sized> fmt::Debug for PsqlWrapper<'_, T> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.value.fmt_psql(f, self.ty)
-    }
+/// Options controlling the SATN data format.
+///
+/// New options may be added in minor releases, so outside of this crate,
+/// start from [`Options::default`] and set the fields of interest:
+///
+/// ```
+/// # use spacetimedb_sats::satn::Options;
+/// let mut opts = Options::default();
+/// opts.canonical = true;
+/// ```
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct Options {
+    /// Prefix each array and product with its length in bytes, written as `{len}`.
+    ///
+    /// The length covers the section from its opening to its closing delimiter,
+    /// so that a machine parser can skip over nested values without parsing them.
+    /// When pretty printing, the indentation preceding a section is not included.
+    ///
+    /// Default: `false`
+    pub length_prefixed: bool,
+    /// The line ending used when pretty printing, e.g. `"\r\n"`.
+    ///
+    /// Default: `"\n"`
+    pub newline: &'static str,
+    /// The maximum number of entries, i.e. array elements and product fields,
+    /// to render across the whole value, regardless of nesting.
+    ///
+    /// Once the budget is exhausted, a single `...` is rendered in place of the
+    /// next entry, and all further entries are omitted.
+    /// The enclosing arrays and products are still closed,
+    /// so the output remains well-formed.
+    ///
+    /// Default: `None`, i.e. unlimited
+    pub max_total_entries: Option<usize>,
+    /// How to render booleans.
+    ///
+    /// Default: [`BoolStyle::Keyword`]
+    pub bool_style: BoolStyle,
+    /// How to render the fields of products which have no name.
+    ///
+    /// Default: [`UnnamedStyle::Indexed`]
+    pub unnamed_style: UnnamedStyle,
+    /// Detect cyclic values and render `<cycle>` instead of recursing forever.
+    ///
+    /// A cycle is detected when an array element or product field
+    /// is found at the same address and of the same type
+    /// as one of the elements or fields enclosing it.
+    /// This can only happen if the value is cyclic,
+    /// e.g. through shared references with interior mutability.
+    ///
+    /// This is opt-in, as tracking the enclosing elements and fields has a cost.
+    ///
+    /// Default: `false`
+    pub detect_cycles: bool,
+    /// How to render byte arrays.
+    ///
+    /// Default: [`BytesStyle::Hex`]
+    pub bytes_style: BytesStyle,
+    /// How to render the variants of sums.
+    ///
+    /// Default: [`VariantStyle::Named`]
+    pub variant_style: VariantStyle,
+    /// Render the fields of named products sorted by name,
+    /// so that the output doesn't depend on the order in which fields are declared.
+    ///
+    /// Products with unnamed fields are rendered in positional order.
+    /// When [`Options::max_total_entries`] is set,
+    /// the budget is consumed in declaration order.
+    ///
+    /// This is opt-in, as the fields of each product are buffered before rendering.
+    ///
+    /// Default: `false`
+    pub canonical: bool,
+    /// Separate groups of three digits of integers with the given character,
+    /// e.g. `Some('_')` renders `1234567` as `1_234_567`.
+    /// With [`IntRadix::Hex`], groups of four digits are separated instead.
+    ///
+    /// This is for display only, machine consumers should not enable it.
+    /// Floats are not affected.
+    ///
+    /// Default: `None`
+    pub digit_grouping: Option<char>,
+    /// Delimit strings with the given character, e.g. `'\''` for SQL-style strings,
+    /// and escape occurrences of it and of backslashes within strings
+    /// with a backslash, e.g. `it's` is rendered as `'it\'s'`.
+    ///
+    /// The delimiter and escaping also apply to the `t"..."` literals of
+    /// [`BytesStyle::TextOrHex`], which are always escaped.
+    /// `None` delimits strings with `"` without escaping their contents.
+    ///
+    /// Default: `None`
+    pub string_quote: Option<char>,
+    /// The case to convert the names of product fields to.
+    ///
+    /// This changes the rendered form, so consumers must expect the converted
+    /// names. Unnamed fields are not affected.
+    ///
+    /// Default: [`KeyCase::AsIs`]
+    pub key_case: KeyCase,
+    /// Render a top-level string without the surrounding quotes, e.g. for
+    /// interpolating it into a larger text.
+    ///
+    /// Newlines and carriage returns are still escaped as `\n` and `\r`.
+    /// Strings nested in arrays, products or sums are always quoted.
+    ///
+    /// Default: `false`
+    pub bare_scalar: bool,
+    /// Escape newlines and carriage returns in strings as `\n` and `\r`,
+    /// so that a compact rendering never spans multiple lines.
+    ///
+    /// Default: `false`
+    pub escape_newlines: bool,
+    /// Render the fields of named products in the given order of names,
+    /// e.g. to pin the output order across versions of a schema.
+    ///
+    /// Fields not listed follow in declaration order,
+    /// or are omitted if [`Options::omit_unlisted_fields`] is set.
+    /// Takes precedence over [`Options::canonical`],
+    /// and is likewise ignored for products with unnamed fields.
+    /// Names are matched before applying [`Options::key_case`].
+    ///
+    /// Default: `&[]`, i.e. declaration order
+    pub field_order: &'static [&'static str],
+    /// Omit the fields not listed in a non-empty [`Options::field_order`].
+    ///
+    /// Default: `false`
+    pub omit_unlisted_fields: bool,
+    /// End the rendering with a single [`Options::newline`], in both compact
+    /// and pretty mode, e.g. for writing it to a file.
+    ///
+    /// Default: `false`
+    pub trailing_newline: bool,
+    /// Truncate strings longer than the given number of bytes,
+    /// marking the truncation inside the quotes with the number of omitted bytes,
+    /// e.g. `"abc…(+42)"`.
+    ///
+    /// Strings are truncated at the last `char` boundary within the limit.
+    /// This is for display only, the omitted part cannot be recovered.
+    ///
+    /// Default: `None`
+    pub string_max_len: Option<usize>,
+    /// When pretty printing, prefix each array element with its index, e.g. `[1]: "b"`.
+    ///
+    /// Inserting an element into an array then visibly shifts the indices of
+    /// the following elements, while each line still holds one element,
+    /// which keeps line-wise diffs of two renderings comparable.
+    /// This is for display only, and has no effect on compact rendering.
+    ///
+    /// Default: `false`
+    pub index_array_elements: bool,
+    /// Render the `none` variant of options as the given keyword,
+    /// e.g. `null`, `NULL`, `nil` or `None`, to match the consumer of the output.
+    ///
+    /// Options are recognized by the name of the variant,
+    /// so this also applies to the `none` variant of other sums.
+    /// `None` renders it like any other variant, i.e. as `(none = ())`.
+    ///
+    /// Default: `None`
+    pub null_keyword: Option<&'static str>,
+    /// Render values nested more than the given number of levels deep as `…`,
+    /// collapsing all elements or fields of an array or product into one marker,
+    /// e.g. `[[…], []]` for a limit of 1.
+    ///
+    /// The top-level value is at level 0, its fields, elements or payload
+    /// are at level 1, and so on. This keeps renderings of deeply nested values,
+    /// e.g. in panic messages, short.
+    ///
+    /// Default: `None`
+    pub max_depth: Option<usize>,
+    /// The radix in which to render integers.
+    ///
+    /// Default: [`IntRadix::Dec`]
+    pub int_radix: IntRadix,
+    /// When pretty printing, render newlines in strings as line breaks
+    /// instead of escaping them as `\n`, for readability.
+    ///
+    /// Each line following a break is indented one level deeper than the line
+    /// the string starts on. To recover the string, remove that indentation
+    /// from each continuation line and join the lines with `\n`.
+    /// Carriage returns are still escaped as `\r`.
+    /// Takes precedence over [`Options::escape_newlines`] when pretty printing.
+    /// Compact renderings are unaffected, and escape newlines
+    /// only if [`Options::escape_newlines`] is set.
+    ///
+    /// Default: `false`
+    pub multiline_strings: bool,
+    /// Render byte arrays longer than the given number of bytes as a summary
+    /// of their length and BLAKE3 hash, e.g. `blob(len=4096, blake3=af13...)`.
+    ///
+    /// This is for display only, the bytes cannot be recovered.
+    /// Hashing requires the `blake3` feature. Without it, this option is
+    /// ignored and byte arrays are rendered in full.
+    ///
+    /// Default: `None`
+    pub blob_hash_threshold: Option<usize>,
+    /// Render a `<redacted>` marker in place of the values of the named fields,
+    /// e.g. to log rows containing PII.
+    ///
+    /// Fields are matched by name at any depth, i.e. in nested products as well.
+    /// Names are matched before applying [`Options::key_case`].
+    /// The value of a redacted field is not serialized at all.
+    ///
+    /// Default: `&[]`, i.e. nothing is redacted
+    pub redact_fields: &'static [&'static str],
+    /// Attach comments to named fields when pretty printing,
+    /// e.g. `age = 42 /* years */`.
+    ///
+    /// The function is called with the name of each field
+    /// and may return `None` to omit the comment.
+    /// Comments are wrapped in `/* */`, so a parser can ignore them.
+    /// Compact renderings are never annotated.
+    ///
+    /// Default: `None`
+    pub annotate: Option<fn(&str) -> Option<String>>,
 }
 
-/// Wraps a writer for formatting lists separated by `SEP` into it.
-struct EntryWrapper<'a, 'f, const SEP: char> {
-    /// The writer we're formatting into.
-    fmt: Writer<'a, 'f>,
-    /// Whether there were any fields.
-    /// Initially `false` and then `true` after calling [`.entry(..)`](EntryWrapper::entry).
-    has_fields: bool,
+/// How to render unnamed product fields, see [`Options::unnamed_style`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnnamedStyle {
+    /// Prefix the value with the index of the field, e.g. `(0 = 1, 1 = 2)`.
+    #[default]
+    Indexed,
+    /// Render only the value, e.g. `(1, 2)`.
+    Positional,
 }
 
-impl<'a, 'f, const SEP: char> EntryWrapper<'a, 'f, SEP> {
-    /// Constructs the entry wrapper using the writer `fmt`.
-    fn new(fmt: Writer<'a, 'f>) -> Self {
-        Self { fmt, has_fields: false }
-    }
+/// How to render byte arrays, see [`Options::bytes_style`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BytesStyle {
+    /// Render as hex, e.g. `0x6869`.
+    #[default]
+    Hex,
+    /// Render as an escaped string literal prefixed with `t`, e.g. `t"hi"`,
+    /// if the bytes are valid UTF-8 without control characters other than whitespace.
+    /// Otherwise, render as hex.
+    /// The literal is delimited and escaped as described in [`Options::string_quote`].
+    ///
+    /// Useful for byte arrays which are mostly, but not always, text.
+    TextOrHex,
+}
 
-    /// Formats another entry in the larger structure.
+/// How to convert the names of product fields, see [`Options::key_case`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyCase {
+    /// Render names as they are.
+    #[default]
+    AsIs,
+    /// Render names in `snake_case`, e.g. `myField` as `my_field`.
+    Snake,
+    /// Render names in `camelCase`, e.g. `my_field` as `myField`.
     ///
-    /// The formatting for the element / entry itself is provided by the function `entry`.
-    fn entry(&mut self, entry: impl FnOnce(Writer) -> fmt::Result) -> fmt::Result {
-        let res = (|| match &mut self.fmt {
-            Writer::Pretty(f) => {
-                if !self.has_fields {
-                    f.write_char('\n')?;
+    /// Leading underscores are retained, other underscores are removed.
+    Camel,
+}
+
+impl KeyCase {
+    /// Converts `name` to this case.
+    fn apply(self, name: &str) -> Cow<'_, str> {
+        match self {
+            Self::AsIs => name.into(),
+            Self::Snake => {
+                let mut out = String::with_capacity(name.len() + 4);
+                let mut prev_lower = false;
+                for c in name.chars() {
+                    if c.is_uppercase() && prev_lower {
+                        out.push('_');
+                    }
+                    prev_lower = c.is_lowercase() || c.is_ascii_digit();
+                    out.extend(c.to_lowercase());
                 }
-                f.state.indent += 1;
-                entry(Writer::Pretty(f.as_mut()))?;
-                f.write_char(SEP)?;
-                f.write_char('\n')?;
-                f.state.indent -= 1;
-                Ok(())
+                out.into()
             }
-            Writer::Normal(f) => {
-                if self.has_fields {
-                    f.write_char(SEP)?;
-                    f.write_char(' ')?;
+            Self::Camel => {
+                let mut out = String::with_capacity(name.len());
+                let mut upper_next = false;
+                for c in name.chars() {
+                    if c == '_' && !out.trim_start_matches('_').is_empty() {
+                        upper_next = true;
+                    } else if upper_next {
+                        out.extend(c.to_uppercase());
+                        upper_next = false;
+                    } else {
+                        out.push(c);
+                    }
                 }
-                entry(Writer::Normal(f))
+                out.into()
             }
-        })();
-        self.has_fields = true;
-        res
+        }
     }
 }
 
-/// An implementation of [`fmt::Write`] supporting indented and non-idented formatting.
-enum Writer<'a, 'f> {
-    /// Uses the standard library's formatter i.e. plain formatting.
-    Normal(&'a mut fmt::Formatter<'f>),
-    /// Uses indented formatting.
-    Pretty(IndentedWriter<'a, 'f>),
+/// How to render sum variants, see [`Options::variant_style`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VariantStyle {
+    /// Render the name of the variant, if known, and the payload, e.g. `(some = 1)`.
+    #[default]
+    Named,
+    /// Render only the tag of the variant and the payload, e.g. `@1(1)`.
+    ///
+    /// The name is never looked at, so this is terse but requires the
+    /// consumer to know the schema.
+    Tag,
 }
 
-impl<'f> Writer<'_, 'f> {
-    /// Provided with a formatter `f`, runs `func` provided with a `Writer`.
-    fn with<R>(f: &mut fmt::Formatter<'_>, func: impl FnOnce(Writer<'_, '_>) -> R) -> R {
-        let mut state;
-        // We use `alternate`, i.e., the `#` flag to let the user trigger pretty printing.
-        let f = if f.alternate() {
-            state = IndentState {
-                indent: 0,
-                on_newline: true,
-            };
-            Writer::Pretty(IndentedWriter { f, state: &mut state })
-        } else {
-            Writer::Normal(f)
-        };
-        func(f)
+/// How to render booleans, see [`Options::bool_style`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BoolStyle {
+    /// Render as `true` or `false`.
+    #[default]
+    Keyword,
+    /// Render as `1` or `0`, for consumers that only understand numbers.
+    Numeric,
+}
+
+/// How to render integers, see [`Options::int_radix`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IntRadix {
+    /// Render in decimal, e.g. `-42`.
+    #[default]
+    Dec,
+    /// Render in hexadecimal, zero-padded to the width of the type,
+    /// e.g. `0x002a` for `42u16`, for debugging bitfield-packed columns.
+    ///
+    /// Signed integers are rendered as their two's complement,
+    /// i.e. `-42i16` is rendered as `0xffd6`, like their bits are stored.
+    /// [`Options::digit_grouping`] separates groups of four digits.
+    Hex,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            length_prefixed: false,
+            newline: "\n",
+            max_total_entries: None,
+            bool_style: BoolStyle::Keyword,
+            unnamed_style: UnnamedStyle::Indexed,
+            detect_cycles: false,
+            bytes_style: BytesStyle::Hex,
+            variant_style: VariantStyle::Named,
+            canonical: false,
+            digit_grouping: None,
+            string_quote: None,
+            key_case: KeyCase::AsIs,
+            bare_scalar: false,
+            escape_newlines: false,
+            field_order: &[],
+            omit_unlisted_fields: false,
+            trailing_newline: false,
+            string_max_len: None,
+            index_array_elements: false,
+            null_keyword: None,
+            max_depth: None,
+            int_radix: IntRadix::Dec,
+            multiline_strings: false,
+            blob_hash_threshold: None,
+            redact_fields: &[],
+            annotate: None,
+        }
     }
+}
 
-    /// Returns a sub-writer without moving `self`.
-    fn as_mut(&mut self) -> Writer<'_, 'f> {
-        match self {
-            Writer::Normal(f) => Writer::Normal(f),
-            Writer::Pretty(f) => Writer::Pretty(f.as_mut()),
+/// The marker rendered in place of omitted entries.
+const TRUNCATED: &str = "...";
+
+/// The marker rendered in place of a cyclic value, see [`Options::detect_cycles`].
+const CYCLE: &str = "<cycle>";
+
+/// The marker rendered in place of a value nested too deeply, see [`Options::max_depth`].
+const ELIDED: &str = "…";
+
+/// The marker rendered in place of the value of a redacted field,
+/// see [`Options::redact_fields`].
+const REDACTED: &str = "<redacted>";
+
+/// The state shared by all formatters rendering a value.
+struct Context {
+    /// The options controlling the format.
+    opts: Options,
+    /// The number of entries that may still be rendered,
+    /// see [`Options::max_total_entries`].
+    remaining: Cell<Option<usize>>,
+    /// Whether the [`TRUNCATED`] marker has been rendered.
+    truncated: Cell<bool>,
+    /// The address and type of the elements and fields currently being rendered,
+    /// see [`Options::detect_cycles`].
+    ancestors: RefCell<Vec<(*const (), &'static str)>>,
+    /// Whether an array, product or sum has been entered,
+    /// i.e. the value being rendered is not at the top level.
+    nested: Cell<bool>,
+    /// The level of the value being rendered, see [`Options::max_depth`].
+    depth: Cell<usize>,
+}
+
+/// What to render for an entry, as decided by [`Context::take_entry`].
+enum Budget {
+    /// Render the entry.
+    Render,
+    /// Render the [`TRUNCATED`] marker instead of the entry.
+    Truncate,
+    /// Render nothing.
+    Skip,
+}
+
+impl Context {
+    fn new(opts: Options) -> Self {
+        Self {
+            opts,
+            remaining: Cell::new(opts.max_total_entries),
+            truncated: Cell::new(false),
+            ancestors: RefCell::default(),
+            nested: Cell::new(false),
+            depth: Cell::new(0),
+        }
+    }
+
+    /// Whether the values nested in the current one are nested too deeply,
+    /// see [`Options::max_depth`].
+    fn elides_entries(&self) -> bool {
+        self.opts.max_depth.is_some_and(|max| self.depth.get() >= max)
+    }
+
+    /// Renders a value nested in the current one into `f` using `render`,
+    /// or the [`ELIDED`] marker if it is nested too deeply.
+    fn nest(&self, mut f: Writer<'_, '_>, render: impl FnOnce(Writer<'_, '_>) -> fmt::Result) -> fmt::Result {
+        if self.elides_entries() {
+            return f.write_str(ELIDED);
+        }
+        self.depth.set(self.depth.get() + 1);
+        let res = render(f);
+        self.depth.set(self.depth.get() - 1);
+        res
+    }
+
+    /// Renders `elem` into `f` using `render`,
+    /// or the [`CYCLE`] marker if `elem` is already being rendered.
+    fn visit<T: ?Sized>(
+        &self,
+        elem: &T,
+        mut f: Writer<'_, '_>,
+        render: impl FnOnce(Writer<'_, '_>) -> fmt::Result,
+    ) -> fmt::Result {
+        if !self.opts.detect_cycles {
+            return render(f);
+        }
+
+        let key = (elem as *const T as *const (), core::any::type_name::<T>());
+        if self.ancestors.borrow().contains(&key) {
+            return f.write_str(CYCLE);
+        }
+        self.ancestors.borrow_mut().push(key);
+        let res = render(f);
+        self.ancestors.borrow_mut().pop();
+        res
+    }
+
+    /// Takes one entry from the budget, deciding what to render for it.
+    fn take_entry(&self) -> Budget {
+        match self.remaining.get() {
+            None => Budget::Render,
+            Some(0) if self.truncated.replace(true) => Budget::Skip,
+            Some(0) => Budget::Truncate,
+            Some(n) => {
+                self.remaining.set(Some(n - 1));
+                Budget::Render
+            }
         }
     }
 }
 
-/// A formatter that adds decoration atop of the standard library's formatter.
-struct IndentedWriter<'a, 'f> {
-    f: &'a mut fmt::Formatter<'f>,
-    state: &'a mut IndentState,
+/// The dimensions of a pretty printed SATN rendering,
+/// see [`Satn::pretty_metrics`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PrettyMetrics {
+    /// The number of lines, as counted by [`str::lines`].
+    pub lines: usize,
+    /// The width of the widest line, in `char`s.
+    pub max_width: usize,
 }
 
-/// The indentation state.
-struct IndentState {
-    /// Number of tab indentations to make.
-    indent: u32,
-    /// Whether we were last on a newline.
-    on_newline: bool,
+/// An implementation of [`fmt::Write`] that only collects [`PrettyMetrics`].
+#[derive(Default)]
+struct MetricsWriter {
+    /// The metrics of the lines completed so far.
+    metrics: PrettyMetrics,
+    /// The width of the current line.
+    width: usize,
 }
 
-impl<'f> IndentedWriter<'_, 'f> {
-    /// Returns a sub-writer without moving `self`.
-    fn as_mut(&mut self) -> IndentedWriter<'_, 'f> {
-        IndentedWriter {
-            f: self.f,
-            state: self.state,
+impl MetricsWriter {
+    /// Returns the metrics, counting the last line if it isn't terminated.
+    fn finish(mut self) -> PrettyMetrics {
+        if self.width > 0 {
+            self.metrics.lines += 1;
         }
+        self.metrics
     }
 }
 
-impl fmt::Write for IndentedWriter<'_, '_> {
+impl fmt::Write for MetricsWriter {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        for s in s.split_inclusive('\n') {
-            if self.state.on_newline {
-                // Indent 4 characters times the indentation level.
-                for _ in 0..self.state.indent {
-                    self.f.write_str("    ")?;
+        for c in s.chars() {
+            match c {
+                '\n' => {
+                    self.metrics.lines += 1;
+                    self.width = 0;
+                }
+                // Not counted by `str::lines`.
+                '\r' => {}
+                _ => {
+                    self.width += 1;
+                    self.metrics.max_width = self.metrics.max_width.max(self.width);
                 }
             }
-
-            self.state.on_newline = s.ends_with('\n');
-            self.f.write_str(s)?;
         }
         Ok(())
     }
 }
 
-impl fmt::Write for Writer<'_, '_> {
-    fn write_str(&mut self, s: &str) -> fmt::Result {
-        match self {
-            Writer::Normal(f) => f.write_str(s),
-            Writer::Pretty(f) => f.write_str(s),
+/// An implementation of [`fmt::Write`] that retains only the last `cap` bytes written,
+/// e.g. for keeping the end of a giant rendering in bounded memory.
+///
+/// Render into it via [`Wrapper`], e.g. `write!(ring, "{}", Wrapper::from_ref(&value))`.
+pub struct RingWriter {
+    /// The bytes written, of which only the last `cap` are retained.
+    /// Compacted once it exceeds twice the capacity, so that discarding is amortized.
+    buf: String,
+    /// The maximum number of bytes to retain.
+    cap: usize,
+}
+
+impl RingWriter {
+    /// Returns a writer retaining at most `cap` bytes.
+    pub fn new(cap: usize) -> Self {
+        Self {
+            buf: String::new(),
+            cap,
         }
     }
+
+    /// Returns the retained tail of the output.
+    ///
+    /// This is at most `cap` bytes long, and shorter than `cap`
+    /// if discarding had to stop at a `char` boundary.
+    pub fn tail(&self) -> &str {
+        &self.buf[ceil_char_boundary(&self.buf, self.buf.len().saturating_sub(self.cap))..]
+    }
 }
 
-/// Provides the SATN data format implementing [`Serializer`](ser::Serializer).
-struct SatnFormatter<'a, 'f> {
-    /// The sink / writer / output / formatter.
-    f: Writer<'a, 'f>,
+impl fmt::Write for RingWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if s.len() >= self.cap {
+            self.buf.clear();
+            self.buf.push_str(&s[ceil_char_boundary(s, s.len() - self.cap)..]);
+        } else {
+            self.buf.push_str(s);
+            if self.buf.len() > self.cap * 2 {
+                let start = ceil_char_boundary(&self.buf, self.buf.len() - self.cap);
+                self.buf.drain(..start);
+            }
+        }
+        Ok(())
+    }
 }
 
-/// An error occured during serialization to the SATS data format.
-#[derive(From, Into)]
-struct SatnError(fmt::Error);
+/// An implementation of [`fmt::Write`] that percent-encodes the output
+/// before writing it to `W`, e.g. for embedding SATN in URLs or shell commands.
+///
+/// All characters but `A-Z`, `a-z`, `0-9` and `_.,=()[]-` are encoded
+/// as the `%XX` escapes of their UTF-8 bytes.
+/// Render into it via [`Wrapper`], e.g. `write!(enc, "{}", Wrapper::from_ref(&value))`,
+/// and use [`percent_decode`] to recover the SATN.
+pub struct PercentEncoder<W>(pub W);
 
-impl ser::Error for SatnError {
-    fn custom<T: fmt::Display>(_msg: T) -> Self {
-        Self(fmt::Error)
+impl<W: fmt::Write> fmt::Write for PercentEncoder<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if c.is_ascii_alphanumeric() || "_.,=()[]-".contains(c) {
+                self.0.write_char(c)?;
+            } else {
+                for b in c.encode_utf8(&mut [0; 4]).bytes() {
+                    write!(self.0, "%{b:02X}")?;
+                }
+            }
+        }
+        Ok(())
     }
 }
 
-impl SatnFormatter<'_, '_> {
-    /// Writes `args` formatted to `self`.
-    #[inline(always)]
-    fn write_fmt(&mut self, args: fmt::Arguments) -> Result<(), SatnError> {
-        self.f.write_fmt(args)?;
-        Ok(())
+/// Decodes the output of [`PercentEncoder`].
+///
+/// Returns `None` if `s` contains a malformed escape,
+/// or if the decoded bytes are not valid UTF-8.
+pub fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        rest = tail;
+        if b == b'%' {
+            let hex = rest.get(..2).filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+            bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            rest = &rest[2..];
+        } else {
+            bytes.push(b);
+        }
     }
+    String::from_utf8(bytes).ok()
 }
 
-impl<'a, 'f> ser::Serializer for SatnFormatter<'a, 'f> {
-    type Ok = ();
-    type Error = SatnError;
-    type SerializeArray = ArrayFormatter<'a, 'f>;
-    type SerializeSeqProduct = SeqFormatter<'a, 'f>;
-    type SerializeNamedProduct = NamedFormatter<'a, 'f>;
+/// Renders a sequence of values as one pretty printed document,
+/// separating consecutive values by a number of blank lines.
+#[derive(Clone, Copy, Debug)]
+pub struct DocumentFormatter {
+    opts: Options,
+    blank_lines: usize,
+}
 
-    fn serialize_bool(mut self, v: bool) -> Result<Self::Ok, Self::Error> {
-        write!(self, "{v}")
+impl DocumentFormatter {
+    /// Separate values by `blank_lines` blank lines, using the default [`Options`].
+    pub fn new(blank_lines: usize) -> Self {
+        Self {
+            opts: Options::default(),
+            blank_lines,
+        }
     }
-    fn serialize_u8(mut self, v: u8) -> Result<Self::Ok, Self::Error> {
-        write!(self, "{v}")
+
+    /// Use `opts` for rendering each value.
+    pub fn with_options(self, opts: Options) -> Self {
+        Self { opts, ..self }
+    }
+
+    /// Pretty prints `values` into the returned `String`.
+    ///
+    /// The document doesn't end with a newline,
+    /// unless [`Options::trailing_newline`] is set.
+    pub fn render(&self, values: impl IntoIterator<Item = impl Satn>) -> String {
+        let value_opts = Options {
+            trailing_newline: false,
+            ..self.opts
+        };
+        let mut out = String::new();
+        for (i, value) in values.into_iter().enumerate() {
+            if i > 0 {
+                out.push_str(&self.opts.newline.repeat(self.blank_lines + 1));
+            }
+            out.push_str(&value.to_satn_pretty_with(&value_opts));
+        }
+        if self.opts.trailing_newline {
+            out.push_str(self.opts.newline);
+        }
+        out
+    }
+}
+
+/// Writes each of `values` in compact SATN to `w`, followed by a newline.
+///
+/// Newlines in strings are escaped, see [`Options::escape_newlines`], so
+/// each line of the output contains exactly one value.
+pub fn write_ndsatn<W: io::Write>(values: impl IntoIterator<Item = impl Satn>, w: &mut W) -> io::Result<()> {
+    write_ndsatn_with(values, &Options::default(), w)
+}
+
+/// Like [`write_ndsatn`], but formats the values according to `opts`.
+///
+/// [`Options::escape_newlines`] is always set
+/// and [`Options::trailing_newline`] is ignored.
+pub fn write_ndsatn_with<W: io::Write>(
+    values: impl IntoIterator<Item = impl Satn>,
+    opts: &Options,
+    w: &mut W,
+) -> io::Result<()> {
+    let opts = Options {
+        escape_newlines: true,
+        trailing_newline: false,
+        ..*opts
+    };
+    for value in values {
+        writeln!(w, "{}", value.to_satn_with(&opts))?;
+    }
+    Ok(())
+}
+
+/// Writes `rows` of the product type `ty` to `w` in columnar SATN,
+/// i.e. a header line naming the fields once,
+/// followed by one line per row with only the values of its fields.
+///
+/// For example, the header `("id", "name")` may be followed by the rows `(1, "a")` and `(2, "b")`.
+/// Field names in the header are rendered as strings,
+/// while unnamed fields are named by their index, e.g. `("id", 1)`.
+/// Values nested in the fields retain their field names.
+/// Strings, including the field names, are escaped with
+/// `"` as [`Options::string_quote`], and, like [`write_ndsatn`],
+/// newlines in strings are escaped, so each line holds exactly one row.
+///
+/// A row with a different number of fields than `ty` results in an error of kind
+/// [`io::ErrorKind::InvalidInput`].
+pub fn write_columnar_satn<'a, W: io::Write>(
+    ty: &ProductType,
+    rows: impl IntoIterator<Item = &'a ProductValue>,
+    w: &mut W,
+) -> io::Result<()> {
+    write_columnar_satn_with(ty, rows, &Options::default(), w)
+}
+
+/// Like [`write_columnar_satn`], but formats the values according to `opts`.
+///
+/// [`Options::escape_newlines`] is always set and [`Options::trailing_newline`] is ignored.
+/// Strings are escaped with [`Options::string_quote`], or with `"` if unset.
+/// Fields of `ty` listed in [`Options::redact_fields`] keep their header name,
+/// but their values are redacted in every row.
+pub fn write_columnar_satn_with<'a, W: io::Write>(
+    ty: &ProductType,
+    rows: impl IntoIterator<Item = &'a ProductValue>,
+    opts: &Options,
+    w: &mut W,
+) -> io::Result<()> {
+    let opts = Options {
+        escape_newlines: true,
+        trailing_newline: false,
+        string_quote: opts.string_quote.or(Some('"')),
+        ..*opts
+    };
+    let names = ty.elements.iter().enumerate().map(|(i, elem)| match &elem.name {
+        Some(name) => name.to_satn_with(&opts),
+        None => i.to_string(),
+    });
+    writeln!(w, "({})", names.collect::<Vec<_>>().join(", "))?;
+
+    for row in rows {
+        if row.elements.len() != ty.elements.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("row has {} fields, expected {}", row.elements.len(), ty.elements.len()),
+            ));
+        }
+        let values = ty.elements.iter().zip(&row.elements).map(|(elem, value)| {
+            if elem.name().is_some_and(|name| opts.redact_fields.contains(&name)) {
+                return REDACTED.to_owned();
+            }
+            WithTypespace::empty(&elem.algebraic_type)
+                .with_value(value)
+                .to_satn_with(&opts)
+        });
+        writeln!(w, "({})", values.collect::<Vec<_>>().join(", "))?;
+    }
+    Ok(())
+}
+
+/// Writes `c` as part of a string literal delimited by `quote`,
+/// escaping the delimiter and backslashes with a backslash,
+/// see [`Options::string_quote`].
+fn write_escaped_char(f: &mut dyn fmt::Write, c: char, quote: Option<char>) -> fmt::Result {
+    if Some(c) == quote || c == '\\' {
+        f.write_char('\\')?;
+    }
+    f.write_char(c)
+}
+
+/// Returns the largest index `<= idx` which is on a `char` boundary of `s`.
+fn floor_char_boundary(s: &str, idx: usize) -> usize {
+    (0..=idx.min(s.len()))
+        .rev()
+        .find(|&i| s.is_char_boundary(i))
+        .unwrap_or(0)
+}
+
+/// Returns the smallest index `>= idx` which is on a `char` boundary of `s`.
+fn ceil_char_boundary(s: &str, idx: usize) -> usize {
+    (idx..s.len()).find(|&i| s.is_char_boundary(i)).unwrap_or(s.len())
+}
+
+/// A wrapper rendering `T` on one line, even when pretty printing.
+///
+/// This allows pretty printing the outer shape of a value,
+/// while keeping, e.g., a huge inner array compact.
+pub struct Compact<T: ?Sized>(pub T);
+
+impl<T: Serialize + ?Sized> Serialize for Compact<T> {
+    fn serialize<S: ser::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        ser.serialize_with_layout(ser::Layout::Compact, &self.0)
+    }
+}
+
+/// A wrapper pretty printing `T`, even when the enclosing value is rendered on one line.
+///
+/// The indentation of `T` starts from zero.
+pub struct Pretty<T: ?Sized>(pub T);
+
+impl<T: Serialize + ?Sized> Serialize for Pretty<T> {
+    fn serialize<S: ser::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        ser.serialize_with_layout(ser::Layout::Pretty, &self.0)
+    }
+}
+
+/// A wrapper around a `T: Satn`
+/// providing `Display` and `Debug` implementations
+/// that uses the SATN formatting for `T`.
+///
+/// The `Debug` implementation is depth-limited, see [`Options::max_depth`].
+#[repr(transparent)]
+pub struct Wrapper<T: ?Sized>(pub T);
+
+impl<T: ?Sized> Wrapper<T> {
+    /// Converts `&T` to `&Wrapper<T>`.
+    pub fn from_ref(t: &T) -> &Self {
+        // SAFETY: `repr(transparent)` turns the ABI of `T`
+        // into the same as `Self` so we can also cast `&T` to `&Self`.
+        unsafe { &*(t as *const T as *const Self) }
+    }
+}
+
+impl<T: Satn + ?Sized> fmt::Display for Wrapper<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The [`Options::max_depth`] of the `Debug` rendering of [`Wrapper`].
+const DEBUG_MAX_DEPTH: usize = 2;
+
+/// Unlike `Display`, renders values nested more than [`DEBUG_MAX_DEPTH`] levels
+/// deep as `…`, keeping e.g. `assert_eq!` failure messages readable.
+impl<T: Satn + ?Sized> fmt::Debug for Wrapper<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let opts = Options {
+            max_depth: Some(DEBUG_MAX_DEPTH),
+            ..Options::default()
+        };
+        self.0.fmt_with(f, &opts)
+    }
+}
+
+/// A wrapper around a `T: Satn`
+/// providing `Display` and `Debug` implementations
+/// that uses postgres SATN formatting for `T`.
+pub struct PsqlWrapper<'a, T: ?Sized> {
+    pub ty: &'a ProductType,
+    pub value: T,
+}
+
+impl<T: ?Sized> PsqlWrapper<'_, T> {
+    /// Converts `&T` to `&PsqlWrapper<T>`.
+    pub fn from_ref(t: &T) -> &Self {
+        // SAFETY: `repr(transparent)` turns the ABI of `T`
+        // into the same as `Self` so we can also cast `&T` to `&Self`.
+        unsafe { &*(t as *const T as *const Self) }
+    }
+}
+
+impl<T: Satn + ?Sized> fmt::Display for PsqlWrapper<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt_psql(f, self.ty)
+    }
+}
+
+impl<T: Satn + ?Sized> fmt::Debug for PsqlWrapper<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt_psql(f, self.ty)
+    }
+}
+
+/// A wrapper around a `T: Satn`
+/// providing a `Display` implementation which prefixes the SATN formatting
+/// of `T` with a type name, e.g. `User(id = 1, name = "x")`.
+///
+/// The `name` is rendered as is if it is a valid identifier,
+/// and otherwise quoted and escaped like a string, e.g. `"user table"(id = 1)`.
+///
+/// The wrapper is meant for product values.
+/// The SATN of any other value is written directly after the name,
+/// e.g. `User42` for a `u32`, which does not delimit the name.
+pub struct NamedSatnWrapper<'a, T: ?Sized> {
+    pub name: &'a str,
+    pub value: T,
+}
+
+impl<T: Satn + ?Sized> fmt::Display for NamedSatnWrapper<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut chars = self.name.chars();
+        let is_ident = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && chars.all(|c| c.is_alphanumeric() || c == '_');
+        if is_ident {
+            f.write_str(self.name)?;
+        } else {
+            f.write_char('"')?;
+            for c in self.name.chars() {
+                write_escaped_char(f, c, Some('"'))?;
+            }
+            f.write_char('"')?;
+        }
+        self.value.fmt(f)
+    }
+}
+
+/// A wrapper around the entries of a map-like collection,
+/// e.g., a `HashMap` or a `BTreeMap`,
+/// providing a [`Serialize`] implementation with a deterministic ordering.
+///
+/// There is no `Serialize` implementation for maps themselves,
+/// as the iteration order of e.g. a `HashMap` is unspecified,
+/// which would make the SATN rendering of equal maps differ.
+/// Instead, `SortedMap` collects the entries and sorts them by key,
+/// so that the output is stable and suitable for diffing.
+///
+/// The entries are serialized as an array of `(key = _, value = _)` products.
+pub struct SortedMap<'a, K, V> {
+    entries: Vec<(&'a K, &'a V)>,
+}
+
+impl<'a, K: Ord, V> SortedMap<'a, K, V> {
+    /// Collects the `entries` of a map, sorting them by key.
+    pub fn new(entries: impl IntoIterator<Item = (&'a K, &'a V)>) -> Self {
+        let mut entries = entries.into_iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(key, _)| *key);
+        Self { entries }
+    }
+}
+
+impl<K: Serialize, V: Serialize> Serialize for SortedMap<'_, K, V> {
+    fn serialize<S: ser::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        use ser::SerializeArray as _;
+
+        let mut entries = ser.serialize_array(self.entries.len())?;
+        for (key, value) in &self.entries {
+            entries.serialize_element(&MapEntry { key, value })?;
+        }
+        entries.end()
+    }
+}
+
+/// Renders `a` and `b` using pretty printed SATN
+/// and returns a line-oriented diff of the two renderings.
+///
+/// Like a unified diff with full context,
+/// each line of the output is prefixed with `-` if it only occurs in the rendering of `a`,
+/// with `+` if it only occurs in the rendering of `b`,
+/// or with a space if it occurs in both.
+/// Within a run of changed lines, the removed lines precede the added ones.
+///
+/// The diff takes time proportional to the product of the numbers of lines
+/// differing between the renderings, but only linear space.
+pub fn diff(a: &(impl Satn + ?Sized), b: &(impl Satn + ?Sized)) -> String {
+    let a = a.to_satn_pretty();
+    let b = b.to_satn_pretty();
+    let a = a.lines().collect::<Vec<_>>();
+    let b = b.lines().collect::<Vec<_>>();
+
+    let mut edits = Vec::with_capacity(a.len().max(b.len()));
+    diff_lines(&a, &b, &mut edits);
+
+    // Within each run of changed lines, render the removed lines first.
+    let mut hunk_start = 0;
+    for i in 0..=edits.len() {
+        if i == edits.len() || edits[i].0 == ' ' {
+            edits[hunk_start..i].sort_by_key(|&(prefix, _)| prefix != '-');
+            hunk_start = i + 1;
+        }
+    }
+
+    let mut out = String::new();
+    for (prefix, line) in edits {
+        out.push(prefix);
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Appends the edits turning `a` into `b` to `edits`,
+/// using Hirschberg's algorithm, i.e. in space linear in the number of lines.
+///
+/// Each edit is a line prefixed as described in [`diff`].
+fn diff_lines<'s>(mut a: &[&'s str], mut b: &[&'s str], edits: &mut Vec<(char, &'s str)>) {
+    // Strip the common prefix and suffix, which is often most of the lines.
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    edits.extend(a[..prefix].iter().map(|&line| (' ', line)));
+    (a, b) = (&a[prefix..], &b[prefix..]);
+    let suffix = a.iter().rev().zip(b.iter().rev()).take_while(|(x, y)| x == y).count();
+    let common_suffix = &a[a.len() - suffix..];
+    (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+
+    match a {
+        [] => edits.extend(b.iter().map(|&line| ('+', line))),
+        [line] => match b.iter().position(|x| x == line) {
+            Some(pos) => {
+                edits.extend(b[..pos].iter().map(|&line| ('+', line)));
+                edits.push((' ', line));
+                edits.extend(b[pos + 1..].iter().map(|&line| ('+', line)));
+            }
+            None => {
+                edits.push(('-', line));
+                edits.extend(b.iter().map(|&line| ('+', line)));
+            }
+        },
+        _ => {
+            // Split `b` where the longest common subsequences
+            // of the two halves of `a` with the parts of `b` are the longest overall.
+            let mid = a.len() / 2;
+            let fwd = lcs_lengths(a[..mid].iter(), b.iter());
+            let bwd = lcs_lengths(a[mid..].iter().rev(), b.iter().rev());
+            let split = (0..=b.len())
+                .max_by_key(|&j| fwd[j] + bwd[b.len() - j])
+                .expect("the range should be non-empty");
+            diff_lines(&a[..mid], &b[..split], edits);
+            diff_lines(&a[mid..], &b[split..], edits);
+        }
+    }
+    edits.extend(common_suffix.iter().map(|&line| (' ', line)));
+}
+
+/// Returns the lengths of the longest common subsequences of `a`
+/// and each prefix of `b`, indexed by the length of the prefix.
+fn lcs_lengths<'a, 's: 'a>(
+    a: impl Iterator<Item = &'a &'s str>,
+    b: impl ExactSizeIterator<Item = &'a &'s str> + Clone,
+) -> Vec<usize> {
+    let mut row = vec![0; b.len() + 1];
+    for x in a {
+        // The entry of the previous row left of the current one.
+        let mut diag = 0;
+        for (j, y) in b.clone().enumerate() {
+            let up = row[j + 1];
+            row[j + 1] = if x == y { diag + 1 } else { up.max(row[j]) };
+            diag = up;
+        }
+    }
+    row
+}
+
+/// An entry of a [`SortedMap`], serialized as a named product.
+struct MapEntry<'a, K, V> {
+    key: &'a K,
+    value: &'a V,
+}
+
+impl<K: Serialize, V: Serialize> Serialize for MapEntry<'_, K, V> {
+    fn serialize<S: ser::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        use ser::SerializeNamedProduct as _;
+
+        let mut prod = ser.serialize_named_product(2)?;
+        prod.serialize_element(Some("key"), self.key)?;
+        prod.serialize_element(Some("value"), self.value)?;
+        prod.end()
+    }
+}
+
+/// Wraps a writer for formatting lists separated by `SEP` into it.
+struct EntryWrapper<'a, 'f, const SEP: char> {
+    /// The writer we're formatting into.
+    fmt: Writer<'a, 'f>,
+    /// Whether there were any fields.
+    /// Initially `false` and then `true` after calling [`.entry(..)`](EntryWrapper::entry).
+    has_fields: bool,
+}
+
+impl<'a, 'f, const SEP: char> EntryWrapper<'a, 'f, SEP> {
+    /// Constructs the entry wrapper using the writer `fmt`.
+    fn new(fmt: Writer<'a, 'f>) -> Self {
+        Self { fmt, has_fields: false }
+    }
+
+    /// Formats another entry in the larger structure.
+    ///
+    /// The formatting for the element / entry itself is provided by the function `entry`.
+    fn entry(&mut self, entry: impl FnOnce(Writer) -> fmt::Result) -> fmt::Result {
+        let res = (|| {
+            if self.fmt.is_pretty() {
+                if !self.has_fields {
+                    self.fmt.newline()?;
+                }
+                self.fmt.indent();
+                entry(self.fmt.as_mut())?;
+                self.fmt.write_char(SEP)?;
+                self.fmt.newline()?;
+                self.fmt.dedent();
+                Ok(())
+            } else {
+                if self.has_fields {
+                    self.fmt.write_char(SEP)?;
+                    self.fmt.write_char(' ')?;
+                }
+                entry(self.fmt.as_mut())
+            }
+        })();
+        self.has_fields = true;
+        res
+    }
+}
+
+/// An implementation of [`fmt::Write`] supporting indented and non-idented formatting.
+enum Writer<'a, 'f> {
+    /// Writes to the output as is, i.e. plain formatting.
+    Normal(&'a mut (dyn fmt::Write + 'f)),
+    /// Uses indented formatting.
+    Pretty(IndentedWriter<'a, 'f>),
+    /// Buffers a section of the output, see [`Framed`].
+    Framed(Box<Framed<'a, 'f>>),
+}
+
+impl<'a, 'f> Writer<'a, 'f> {
+    /// Provided with an output `f`, runs `func` provided with a `Writer`.
+    ///
+    /// The writer uses indented formatting if `pretty` is set,
+    /// with the line endings configured in `opts`.
+    fn with<R>(f: &mut dyn fmt::Write, pretty: bool, opts: &Options, func: impl FnOnce(Writer<'_, '_>) -> R) -> R {
+        let mut state;
+        let f = if pretty {
+            state = IndentState {
+                indent: 0,
+                on_newline: true,
+                newline: opts.newline,
+            };
+            Writer::Pretty(IndentedWriter { f, state: &mut state })
+        } else {
+            Writer::Normal(f)
+        };
+        func(f)
+    }
+
+    /// Like [`Writer::with`], but continues a line of indented formatting
+    /// at the level `indent`, if set.
+    ///
+    /// The indentation of the first line is left to the consumer of the output,
+    /// see [`Writer::write_indented`].
+    fn continuing<R>(
+        f: &mut dyn fmt::Write,
+        indent: Option<u32>,
+        opts: &Options,
+        func: impl FnOnce(Writer<'_, '_>) -> R,
+    ) -> R {
+        let mut state;
+        let f = match indent {
+            Some(indent) => {
+                state = IndentState {
+                    indent,
+                    on_newline: false,
+                    newline: opts.newline,
+                };
+                Writer::Pretty(IndentedWriter { f, state: &mut state })
+            }
+            None => Writer::Normal(f),
+        };
+        func(f)
+    }
+
+    /// Writes `s`, rendered by [`Writer::continuing`] at the current indentation level.
+    ///
+    /// Only the first line of `s` is indented, the others are indented already.
+    /// Length prefixes within `s` thus remain accurate.
+    fn write_indented(&mut self, s: &str) -> fmt::Result {
+        let indent = self.indent_state().map(|state| {
+            let indent = if state.on_newline { state.indent } else { 0 };
+            state.on_newline = s.ends_with(state.newline);
+            indent
+        });
+        for _ in 0..indent.unwrap_or_default() {
+            self.raw().write_str(INDENT)?;
+        }
+        self.raw().write_str(s)
+    }
+
+    /// Returns a sub-writer without moving `self`.
+    fn as_mut(&mut self) -> Writer<'_, 'f> {
+        match self {
+            Writer::Normal(f) => Writer::Normal(&mut **f),
+            Writer::Pretty(f) => Writer::Pretty(f.as_mut()),
+            Writer::Framed(f) => f.writer(),
+        }
+    }
+
+    /// Returns the indentation state, if formatting is indented.
+    fn indent_state(&mut self) -> Option<&mut IndentState> {
+        match self {
+            Writer::Normal(_) => None,
+            Writer::Pretty(f) => Some(&mut *f.state),
+            Writer::Framed(f) => f.parent.indent_state(),
+        }
+    }
+
+    /// Returns whether formatting is indented.
+    fn is_pretty(&mut self) -> bool {
+        self.indent_state().is_some()
+    }
+
+    /// Writes a line ending, if formatting is indented.
+    fn newline(&mut self) -> fmt::Result {
+        match self.indent_state() {
+            Some(state) => {
+                let newline = state.newline;
+                self.write_str(newline)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Increases the indentation level by one, if formatting is indented.
+    fn indent(&mut self) {
+        if let Some(state) = self.indent_state() {
+            state.indent += 1;
+        }
+    }
+
+    /// Decreases the indentation level by one, if formatting is indented.
+    fn dedent(&mut self) {
+        if let Some(state) = self.indent_state() {
+            state.indent -= 1;
+        }
+    }
+
+    /// Returns the underlying output, bypassing any indentation.
+    fn raw(&mut self) -> &mut (dyn fmt::Write + 'f) {
+        match self {
+            Writer::Normal(f) => &mut **f,
+            Writer::Pretty(f) => &mut *f.f,
+            Writer::Framed(f) => &mut f.buf,
+        }
+    }
+
+    /// Starts a section of the output which is written to `self`,
+    /// prefixed by its length, once [`finish`](Writer::finish)ed.
+    fn framed(mut self) -> Result<Self, fmt::Error> {
+        // Indent up front, so that the length prefix comes after the indentation
+        // and the indentation is not counted towards the length.
+        let indent = self.indent_state().filter(|state| state.on_newline).map(|state| {
+            state.on_newline = false;
+            state.indent
+        });
+        for _ in 0..indent.unwrap_or_default() {
+            self.raw().write_str(INDENT)?;
+        }
+
+        Ok(Writer::Framed(Box::new(Framed {
+            parent: self,
+            buf: String::new(),
+        })))
+    }
+
+    /// Finishes writing to `self`, flushing any buffered section.
+    fn finish(self) -> fmt::Result {
+        match self {
+            Writer::Framed(f) => f.finish(),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A section of the output, buffered so that its length in bytes
+/// can be written before it, as configured by [`Options::length_prefixed`].
+struct Framed<'a, 'f> {
+    /// The writer the section is written to once finished.
+    parent: Writer<'a, 'f>,
+    /// The section written so far.
+    buf: String,
+}
+
+impl<'f> Framed<'_, 'f> {
+    /// Returns a sub-writer writing to the buffered section.
+    fn writer(&mut self) -> Writer<'_, 'f> {
+        match self.parent.indent_state() {
+            Some(state) => Writer::Pretty(IndentedWriter {
+                f: &mut self.buf,
+                state,
+            }),
+            None => Writer::Normal(&mut self.buf),
+        }
+    }
+
+    /// Writes the length of the section followed by the section itself to the parent.
+    fn finish(mut self) -> fmt::Result {
+        write!(self.parent, "{{{}}}", self.buf.len())?;
+        // The section is already indented,
+        // and the indentation state reflects its end.
+        self.parent.raw().write_str(&self.buf)?;
+        self.parent.finish()
+    }
+}
+
+/// The indentation for one level of pretty printing.
+const INDENT: &str = "    ";
+
+/// A formatter that adds decoration atop of the standard library's formatter.
+struct IndentedWriter<'a, 'f> {
+    f: &'a mut (dyn fmt::Write + 'f),
+    state: &'a mut IndentState,
+}
+
+/// The indentation state.
+struct IndentState {
+    /// Number of tab indentations to make.
+    indent: u32,
+    /// Whether we were last on a newline.
+    on_newline: bool,
+    /// The line ending, see [`Options::newline`].
+    newline: &'static str,
+}
+
+impl<'f> IndentedWriter<'_, 'f> {
+    /// Returns a sub-writer without moving `self`.
+    fn as_mut(&mut self) -> IndentedWriter<'_, 'f> {
+        IndentedWriter {
+            f: self.f,
+            state: self.state,
+        }
+    }
+}
+
+impl fmt::Write for IndentedWriter<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let newline = self.state.newline;
+        for s in s.split_inclusive(newline) {
+            if self.state.on_newline {
+                // Indent 4 characters times the indentation level.
+                for _ in 0..self.state.indent {
+                    self.f.write_str(INDENT)?;
+                }
+            }
+
+            self.state.on_newline = s.ends_with(newline);
+            self.f.write_str(s)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Write for Writer<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self {
+            Writer::Normal(f) => f.write_str(s),
+            Writer::Pretty(f) => f.write_str(s),
+            Writer::Framed(f) => f.writer().write_str(s),
+        }
+    }
+}
+
+/// Writes the float `v`, an `f32` or `f64`, to `f`.
+///
+/// This is the single source of truth for rendering floats,
+/// and the rendering is guaranteed to be stable across versions of this crate:
+///
+/// - finite values are rendered in positional notation, never with an exponent,
+///   using the fewest digits which parse back to `v`,
+///   e.g. `1.5`, `0.1`, `1e21` as `1000000000000000000000`, and subnormals as `0.000…5`
+/// - integral values have no fractional part, e.g. `1.0` as `1`
+/// - the sign of negative zero is retained, i.e. `-0.0` as `-0`
+/// - non-finite values are rendered as `NaN`, `inf` and `-inf`
+/// - the decimal separator is always `.`, independent of the locale
+///
+/// This matches the `Display` impls of `f32` and `f64` at the time of writing.
+/// Should those ever change, this function has to retain the rendering above,
+/// as verified by the `float_rendering_is_stable` test.
+fn write_float(f: &mut (impl fmt::Write + ?Sized), v: impl fmt::Display) -> fmt::Result {
+    write!(f, "{v}")
+}
+
+/// Provides the SATN data format implementing [`Serializer`](ser::Serializer).
+struct SatnFormatter<'a, 'f> {
+    /// The sink / writer / output / formatter.
+    f: Writer<'a, 'f>,
+    /// The formatting context shared by the whole value.
+    cx: &'a Context,
+}
+
+/// An error occured during serialization to the SATS data format.
+#[derive(Debug, From, Into)]
+struct SatnError(fmt::Error);
+
+impl ser::Error for SatnError {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        Self(fmt::Error)
+    }
+}
+
+impl SatnFormatter<'_, '_> {
+    /// Writes `args` formatted to `self`.
+    #[inline(always)]
+    fn write_fmt(&mut self, args: fmt::Arguments) -> Result<(), SatnError> {
+        self.f.write_fmt(args)?;
+        Ok(())
+    }
+
+    /// Writes the integer `v` to `self` in the configured radix,
+    /// grouping its digits if configured,
+    /// see [`Options::int_radix`] and [`Options::digit_grouping`].
+    fn write_int<T: fmt::Display + fmt::LowerHex>(&mut self, v: T) -> Result<(), SatnError> {
+        let hex = self.cx.opts.int_radix == IntRadix::Hex;
+        // Two hex digits per byte.
+        let width = mem::size_of::<T>() * 2;
+        let Some(sep) = self.cx.opts.digit_grouping else {
+            return if hex {
+                write!(self, "0x{v:0width$x}")
+            } else {
+                write!(self, "{v}")
+            };
+        };
+        let (v, group) = if hex {
+            self.f.write_str("0x")?;
+            (format!("{v:0width$x}"), 4)
+        } else {
+            (v.to_string(), 3)
+        };
+        let (sign, digits) = v.split_at(v.starts_with('-') as usize);
+        self.f.write_str(sign)?;
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % group == 0 {
+                self.f.write_char(sep)?;
+            }
+            self.f.write_char(digit)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'f> ser::Serializer for SatnFormatter<'a, 'f> {
+    type Ok = ();
+    type Error = SatnError;
+    type SerializeArray = ArrayFormatter<'a, 'f>;
+    type SerializeSeqProduct = SeqFormatter<'a, 'f>;
+    type SerializeNamedProduct = NamedFormatter<'a, 'f>;
+
+    fn serialize_bool(mut self, v: bool) -> Result<Self::Ok, Self::Error> {
+        match self.cx.opts.bool_style {
+            BoolStyle::Keyword => write!(self, "{v}"),
+            BoolStyle::Numeric => write!(self, "{}", v as u8),
+        }
+    }
+    fn serialize_u8(mut self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.write_int(v)
     }
     fn serialize_u16(mut self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.write_int(v)
+    }
+    fn serialize_u32(mut self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.write_int(v)
+    }
+    fn serialize_u64(mut self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.write_int(v)
+    }
+    fn serialize_u128(mut self, v: u128) -> Result<Self::Ok, Self::Error> {
+        self.write_int(v)
+    }
+    fn serialize_u256(mut self, v: u256) -> Result<Self::Ok, Self::Error> {
+        self.write_int(v)
+    }
+    fn serialize_i8(mut self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.write_int(v)
+    }
+    fn serialize_i16(mut self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.write_int(v)
+    }
+    fn serialize_i32(mut self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.write_int(v)
+    }
+    fn serialize_i64(mut self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.write_int(v)
+    }
+    fn serialize_i128(mut self, v: i128) -> Result<Self::Ok, Self::Error> {
+        self.write_int(v)
+    }
+    fn serialize_i256(mut self, v: i256) -> Result<Self::Ok, Self::Error> {
+        self.write_int(v)
+    }
+    fn serialize_f32(mut self, v: f32) -> Result<Self::Ok, Self::Error> {
+        write_float(&mut self.f, v)?;
+        Ok(())
+    }
+    fn serialize_f64(mut self, v: f64) -> Result<Self::Ok, Self::Error> {
+        write_float(&mut self.f, v)?;
+        Ok(())
+    }
+
+    fn serialize_str(mut self, v: &str) -> Result<Self::Ok, Self::Error> {
+        let opts = &self.cx.opts;
+        let bare = opts.bare_scalar && !self.cx.nested.get();
+        let quote = (!bare).then(|| opts.string_quote.unwrap_or('"'));
+        let escape = !bare && opts.string_quote.is_some();
+        let multiline = !bare && opts.multiline_strings && self.f.is_pretty();
+        let escape_newlines = bare || opts.escape_newlines;
+        let (v, omitted) = match opts.string_max_len {
+            Some(max) if v.len() > max => {
+                let end = floor_char_boundary(v, max);
+                (&v[..end], v.len() - end)
+            }
+            _ => (v, 0),
+        };
+
+        if let Some(quote) = quote {
+            self.f.write_char(quote)?;
+        }
+        if multiline {
+            self.f.indent();
+        }
+        for c in v.chars() {
+            match c {
+                '\n' if multiline => self.f.newline()?,
+                '\r' if multiline => self.f.write_str("\\r")?,
+                '\n' if escape_newlines => self.f.write_str("\\n")?,
+                '\r' if escape_newlines => self.f.write_str("\\r")?,
+                c if escape => write_escaped_char(&mut self.f, c, quote)?,
+                c => self.f.write_char(c)?,
+            }
+        }
+        if omitted > 0 {
+            write!(self, "…(+{omitted})")?;
+        }
+        if let Some(quote) = quote {
+            self.f.write_char(quote)?;
+        }
+        if multiline {
+            self.f.dedent();
+        }
+        Ok(())
+    }
+
+    fn serialize_bytes(mut self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        #[cfg(feature = "blake3")]
+        if self.cx.opts.blob_hash_threshold.is_some_and(|max| v.len() > max) {
+            return write!(self, "blob(len={}, blake3={})", v.len(), blake3::hash(v).to_hex());
+        }
+        if self.cx.opts.bytes_style == BytesStyle::TextOrHex {
+            let text = std::str::from_utf8(v)
+                .ok()
+                .filter(|s| s.chars().all(|c| !c.is_control() || c.is_ascii_whitespace()));
+            if let Some(text) = text {
+                let quote = self.cx.opts.string_quote.unwrap_or('"');
+                write!(self, "t{quote}")?;
+                for c in text.chars() {
+                    if c.is_control() {
+                        write!(self, "{}", c.escape_debug())?;
+                    } else {
+                        write_escaped_char(&mut self.f, c, Some(quote))?;
+                    }
+                }
+                return write!(self, "{quote}");
+            }
+        }
+        write!(self, "0x{}", hex::encode(v))
+    }
+
+    fn serialize_array(mut self, _len: usize) -> Result<Self::SerializeArray, Self::Error> {
+        self.cx.nested.set(true);
+        if self.cx.opts.length_prefixed {
+            self.f = self.f.framed()?;
+        }
+        write!(self, "[")?; // Closed via `.end()`.
+        Ok(ArrayFormatter {
+            f: EntryWrapper::new(self.f),
+            cx: self.cx,
+            index: 0,
+        })
+    }
+
+    fn serialize_seq_product(self, len: usize) -> Result<Self::SerializeSeqProduct, Self::Error> {
+        // Delegate to named products handling of element formatting.
+        self.serialize_named_product(len).map(|inner| SeqFormatter { inner })
+    }
+
+    fn serialize_named_product(mut self, _len: usize) -> Result<Self::SerializeNamedProduct, Self::Error> {
+        self.cx.nested.set(true);
+        if self.cx.opts.length_prefixed {
+            self.f = self.f.framed()?;
+        }
+        write!(self, "(")?; // Closed via `.end()`.
+        Ok(NamedFormatter {
+            f: EntryWrapper::new(self.f),
+            cx: self.cx,
+            idx: 0,
+            buffered: (self.cx.opts.canonical || !self.cx.opts.field_order.is_empty()).then(Vec::new),
+            truncated: false,
+        })
+    }
+
+    fn serialize_variant<T: ser::Serialize + ?Sized>(
+        mut self,
+        tag: u8,
+        name: Option<&str>,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        let cx = self.cx;
+        if let Some(keyword) = cx.opts.null_keyword.filter(|_| name == Some(OPTION_NONE_TAG)) {
+            return write!(self, "{}", keyword);
+        }
+        cx.nested.set(true);
+        if cx.opts.variant_style == VariantStyle::Tag {
+            write!(self, "@{tag}(")?;
+            cx.nest(self.f.as_mut(), |f| {
+                value.serialize(SatnFormatter { f, cx }).map_err(|e| e.0)
+            })?;
+            return write!(self, ")");
+        }
+        write!(self, "(")?;
+        EntryWrapper::<','>::new(self.f.as_mut()).entry(|mut f| {
+            if let Some(name) = name {
+                write!(f, "{}", name)?;
+            }
+            write!(f, " = ")?;
+            cx.nest(f, |f| value.serialize(SatnFormatter { f, cx }).map_err(|e| e.0))?;
+            Ok(())
+        })?;
+        write!(self, ")")
+    }
+
+    fn serialize_with_layout<T: ser::Serialize + ?Sized>(
+        mut self,
+        layout: ser::Layout,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        let pretty = layout == ser::Layout::Pretty;
+        if self.f.is_pretty() == pretty {
+            return value.serialize(self);
+        }
+        let cx = self.cx;
+        Writer::with(&mut self.f, pretty, &cx.opts, |f| {
+            value.serialize(SatnFormatter { f, cx })
+        })
+    }
+
+    unsafe fn serialize_bsatn(self, ty: &crate::AlgebraicType, bsatn: &[u8]) -> Result<Self::Ok, Self::Error> {
+        // TODO(Centril): Consider instead deserializing the `bsatn` through a
+        // deserializer that serializes into `self` directly.
+
+        // First convert the BSATN to an `AlgebraicValue`.
+        // SAFETY: Forward caller requirements of this method to that we are calling.
+        let res = unsafe { ValueSerializer.serialize_bsatn(ty, bsatn) };
+        let value = res.unwrap_or_else(|x| match x {});
+
+        // Then serialize that.
+        value.serialize(self)
+    }
+
+    unsafe fn serialize_bsatn_in_chunks<'c, I: Clone + Iterator<Item = &'c [u8]>>(
+        self,
+        ty: &crate::AlgebraicType,
+        total_bsatn_len: usize,
+        bsatn: I,
+    ) -> Result<Self::Ok, Self::Error> {
+        // TODO(Centril): Unlike above, in this case we must at minimum concatenate `bsatn`
+        // before we can do the piping mentioned above, but that's better than
+        // serializing to `AlgebraicValue` first, so consider that.
+
+        // First convert the BSATN to an `AlgebraicValue`.
+        // SAFETY: Forward caller requirements of this method to that we are calling.
+        let res = unsafe { ValueSerializer.serialize_bsatn_in_chunks(ty, total_bsatn_len, bsatn) };
+        let value = res.unwrap_or_else(|x| match x {});
+
+        // Then serialize that.
+        value.serialize(self)
+    }
+
+    unsafe fn serialize_str_in_chunks<'c, I: Clone + Iterator<Item = &'c [u8]>>(
+        self,
+        total_len: usize,
+        string: I,
+    ) -> Result<Self::Ok, Self::Error> {
+        // First convert the `string` to an `AlgebraicValue`.
+        // SAFETY: Forward caller requirements of this method to that we are calling.
+        let res = unsafe { ValueSerializer.serialize_str_in_chunks(total_len, string) };
+        let value = res.unwrap_or_else(|x| match x {});
+
+        // Then serialize that.
+        // This incurs a very minor cost of branching on `AlgebraicValue::String`.
+        value.serialize(self)
+    }
+}
+
+/// Defines the SATN formatting for arrays.
+struct ArrayFormatter<'a, 'f> {
+    /// The formatter for each element separating elements by a `,`.
+    f: EntryWrapper<'a, 'f, ','>,
+    /// The formatting context shared by the whole value.
+    cx: &'a Context,
+    /// The index of the next element, see [`Options::index_array_elements`].
+    index: usize,
+}
+
+impl ser::SerializeArray for ArrayFormatter<'_, '_> {
+    type Ok = ();
+    type Error = SatnError;
+
+    fn serialize_element<T: ser::Serialize + ?Sized>(&mut self, elem: &T) -> Result<(), Self::Error> {
+        let cx = self.cx;
+        let index = self.index;
+        self.index += 1;
+        if cx.elides_entries() {
+            // Collapse all elements into a single marker.
+            if index == 0 {
+                self.f.entry(|mut f| f.write_str(ELIDED))?;
+            }
+            return Ok(());
+        }
+        match cx.take_entry() {
+            Budget::Render => self.f.entry(|mut f| {
+                if cx.opts.index_array_elements && f.is_pretty() {
+                    write!(f, "[{index}]: ")?;
+                }
+                cx.nest(f, |f| {
+                    cx.visit(elem, f, |f| elem.serialize(SatnFormatter { f, cx }).map_err(|e| e.0))
+                })
+            })?,
+            Budget::Truncate => self.f.entry(|mut f| f.write_str(TRUNCATED))?,
+            Budget::Skip => {}
+        }
+        Ok(())
+    }
+
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        write!(self.f.fmt, "]")?;
+        self.f.fmt.finish()?;
+        Ok(())
+    }
+}
+
+/// Provides the data format for unnamed products for SATN.
+struct SeqFormatter<'a, 'f> {
+    /// Delegates to the named format.
+    inner: NamedFormatter<'a, 'f>,
+}
+
+impl ser::SerializeSeqProduct for SeqFormatter<'_, '_> {
+    type Ok = ();
+    type Error = SatnError;
+
+    fn serialize_element<T: ser::Serialize + ?Sized>(&mut self, elem: &T) -> Result<(), Self::Error> {
+        ser::SerializeNamedProduct::serialize_element(&mut self.inner, None, elem)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeNamedProduct::end(self.inner)
+    }
+}
+
+/// Provides the data format for named products for SATN.
+struct NamedFormatter<'a, 'f> {
+    /// The formatter for each element separating elements by a `,`.
+    f: EntryWrapper<'a, 'f, ','>,
+    /// The formatting context shared by the whole value.
+    cx: &'a Context,
+    /// The index of the element.
+    idx: usize,
+    /// The names and renderings of the fields rendered so far,
+    /// if they are to be reordered in `.end()`,
+    /// see [`Options::canonical`] and [`Options::field_order`].
+    buffered: Option<Vec<(Option<String>, String)>>,
+    /// Whether the [`TRUNCATED`] marker is to be rendered after the buffered fields.
+    truncated: bool,
+}
+
+impl NamedFormatter<'_, '_> {
+    /// Renders the field `name` at index `idx` with value `elem`, sans separator.
+    fn field<T: ser::Serialize + ?Sized>(
+        cx: &Context,
+        mut f: Writer<'_, '_>,
+        idx: usize,
+        name: Option<&str>,
+        elem: &T,
+    ) -> fmt::Result {
+        // Format the name or use the index if unnamed.
+        match (name, cx.opts.unnamed_style) {
+            (Some(name), _) => write!(f, "{} = ", cx.opts.key_case.apply(name))?,
+            (None, UnnamedStyle::Indexed) => write!(f, "{} = ", idx)?,
+            (None, UnnamedStyle::Positional) => {}
+        }
+        if name.is_some_and(|name| cx.opts.redact_fields.contains(&name)) {
+            f.write_str(REDACTED)?;
+        } else {
+            cx.nest(f.as_mut(), |f| {
+                cx.visit(elem, f, |f| elem.serialize(SatnFormatter { f, cx }).map_err(|e| e.0))
+            })?;
+        }
+        // Only annotate in pretty mode, where each field is on its own line.
+        if let Some((name, annotate)) = name.zip(cx.opts.annotate).filter(|_| f.is_pretty()) {
+            if let Some(comment) = annotate(name) {
+                // Make sure the comment cannot be terminated early.
+                write!(f, " /* {} */", comment.replace("*/", "* /"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeNamedProduct for NamedFormatter<'_, '_> {
+    type Ok = ();
+    type Error = SatnError;
+
+    fn serialize_element<T: ser::Serialize + ?Sized>(
+        &mut self,
+        name: Option<&str>,
+        elem: &T,
+    ) -> Result<(), Self::Error> {
+        if self.cx.elides_entries() {
+            // Collapse all fields into a single marker.
+            if self.idx == 0 {
+                self.f.entry(|mut f| f.write_str(ELIDED))?;
+            }
+            self.idx += 1;
+            return Ok(());
+        }
+        let res = match (self.cx.take_entry(), self.buffered.is_some()) {
+            (Budget::Render, false) => self.f.entry(|f| Self::field(self.cx, f, self.idx, name, elem)),
+            (Budget::Render, true) => {
+                // Render the field on its own, at the indentation level of an entry,
+                // so that the lengths of any framed sections within it are accurate.
+                let mut out = String::new();
+                let indent = self.f.fmt.indent_state().map(|state| state.indent + 1);
+                let res = Writer::continuing(&mut out, indent, &self.cx.opts, |f| {
+                    Self::field(self.cx, f, self.idx, name, elem)
+                });
+                if let Some(buffered) = &mut self.buffered {
+                    buffered.push((name.map(Into::into), out));
+                }
+                res
+            }
+            (Budget::Truncate, false) => self.f.entry(|mut f| f.write_str(TRUNCATED)),
+            (Budget::Truncate, true) => {
+                self.truncated = true;
+                Ok(())
+            }
+            (Budget::Skip, _) => Ok(()),
+        };
+        self.idx += 1;
+        res?;
+        Ok(())
+    }
+
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        if let Some(mut buffered) = self.buffered.take() {
+            let opts = &self.cx.opts;
+            if buffered.iter().all(|(name, _)| name.is_some()) {
+                if !opts.field_order.is_empty() {
+                    let position = |name: &Option<String>| {
+                        let name = name.as_deref().unwrap_or_default();
+                        opts.field_order.iter().position(|&listed| listed == name)
+                    };
+                    if opts.omit_unlisted_fields {
+                        buffered.retain(|(name, _)| position(name).is_some());
+                    }
+                    // Stable, so unlisted fields stay in declaration order.
+                    buffered.sort_by_key(|(name, _)| position(name).unwrap_or(usize::MAX));
+                } else {
+                    buffered.sort_by_cached_key(|(name, _)| {
+                        name.as_deref().map(|name| opts.key_case.apply(name).into_owned())
+                    });
+                }
+            }
+            for (_, field) in buffered {
+                self.f.entry(|mut f| f.write_indented(&field))?;
+            }
+            if self.truncated {
+                self.f.entry(|mut f| f.write_str(TRUNCATED))?;
+            }
+        }
+        write!(self.f.fmt, ")")?;
+        self.f.fmt.finish()?;
+        Ok(())
+    }
+}
+
+/// Provides the data format for named products for `SQL`.
+struct PsqlNamedFormatter<'a, 'f> {
+    /// The formatter for each element separating elements by a `,`.
+    f: EntryWrapper<'a, 'f, ','>,
+    /// The formatting context shared by the whole value.
+    cx: &'a Context,
+    /// The index of the element.
+    idx: usize,
+    /// If is not [Self::is_bytes_or_special] to control if we start with `(`
+    start: bool,
+    /// For checking [Self::is_bytes_or_special]
+    ty: &'a ProductType,
+    /// If the current element is a special type.
+    is_special: bool,
+}
+
+impl ser::SerializeNamedProduct for PsqlNamedFormatter<'_, '_> {
+    type Ok = ();
+    type Error = SatnError;
+
+    fn serialize_element<T: Satn + ser::Serialize + ?Sized>(
+        &mut self,
+        name: Option<&str>,
+        elem: &T,
+    ) -> Result<(), Self::Error> {
+        // For binary data, output in `hex` format and skip the tagging of each value
+        self.is_special = ProductType::is_special_tag(name.unwrap_or_default());
+        self.f.entry(|mut f| {
+            if !self.is_special {
+                if self.start {
+                    write!(f, "(")?; // Closed v
+                    self.start = false;
+                }
+                // Format the name or use the index if unnamed.
+                if let Some(name) = name {
+                    write!(f, "{}", name)?;
+                } else {
+                    write!(f, "{}", self.idx)?;
+                }
+                write!(f, " = ")?;
+            }
+
+            elem.serialize(PsqlFormatter {
+                fmt: SatnFormatter { f, cx: self.cx },
+                ty: self.ty,
+                is_char: name == Some(CHAR_TAG),
+            })?;
+
+            if !self.is_special {
+                self.idx += 1;
+            }
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        if !self.is_special {
+            write!(self.f.fmt, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// Provides the data format for unnamed products for `SQL`.
+struct PsqlSeqFormatter<'a, 'f> {
+    /// Delegates to the named format.
+    inner: PsqlNamedFormatter<'a, 'f>,
+}
+
+impl ser::SerializeSeqProduct for PsqlSeqFormatter<'_, '_> {
+    type Ok = ();
+    type Error = SatnError;
+
+    fn serialize_element<T: ser::Serialize + ?Sized>(&mut self, elem: &T) -> Result<(), Self::Error> {
+        ser::SerializeNamedProduct::serialize_element(&mut self.inner, None, elem)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeNamedProduct::end(self.inner)
+    }
+}
+
+/// An implementation of [`Serializer`](ser::Serializer) for `SQL` output.
+struct PsqlFormatter<'a, 'f> {
+    fmt: SatnFormatter<'a, 'f>,
+    ty: &'a ProductType,
+    /// If the value is tagged with [`CHAR_TAG`] and a `u32` should be rendered as a character.
+    is_char: bool,
+}
+
+impl<'a, 'f> ser::Serializer for PsqlFormatter<'a, 'f> {
+    type Ok = ();
+    type Error = SatnError;
+    type SerializeArray = ArrayFormatter<'a, 'f>;
+    type SerializeSeqProduct = PsqlSeqFormatter<'a, 'f>;
+    type SerializeNamedProduct = PsqlNamedFormatter<'a, 'f>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_bool(v)
+    }
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_u8(v)
+    }
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_u16(v)
+    }
+    fn serialize_u32(mut self, v: u32) -> Result<Self::Ok, Self::Error> {
+        // Surrogates and out-of-range values fall back to the integer.
+        match char::from_u32(v).filter(|_| self.is_char) {
+            Some(c) => write!(self.fmt, "'{}'", c.escape_debug()),
+            None => self.fmt.serialize_u32(v),
+        }
+    }
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_u64(v)
+    }
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_u128(v)
+    }
+    fn serialize_u256(self, v: u256) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_u256(v)
+    }
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_i8(v)
+    }
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_i16(v)
+    }
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_i32(v)
+    }
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_i64(v)
+    }
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_i128(v)
+    }
+    fn serialize_i256(self, v: i256) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_i256(v)
+    }
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_f32(v)
+    }
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_f64(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_str(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_bytes(v)
+    }
+
+    fn serialize_array(self, len: usize) -> Result<Self::SerializeArray, Self::Error> {
+        self.fmt.serialize_array(len)
+    }
+
+    fn serialize_seq_product(self, len: usize) -> Result<Self::SerializeSeqProduct, Self::Error> {
+        Ok(PsqlSeqFormatter {
+            inner: self.serialize_named_product(len)?,
+        })
+    }
+
+    fn serialize_named_product(self, _len: usize) -> Result<Self::SerializeNamedProduct, Self::Error> {
+        Ok(PsqlNamedFormatter {
+            f: EntryWrapper::new(self.fmt.f),
+            cx: self.fmt.cx,
+            idx: 0,
+            start: true,
+            ty: self.ty,
+            is_special: false,
+        })
+    }
+
+    fn serialize_variant<T: ser::Serialize + ?Sized>(
+        self,
+        tag: u8,
+        name: Option<&str>,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.fmt.serialize_variant(tag, name, value)
+    }
+
+    unsafe fn serialize_bsatn(self, ty: &crate::AlgebraicType, bsatn: &[u8]) -> Result<Self::Ok, Self::Error> {
+        // SAFETY: Forward caller requirements of this method to that we are calling.
+        unsafe { self.fmt.serialize_bsatn(ty, bsatn) }
+    }
+
+    unsafe fn serialize_bsatn_in_chunks<'c, I: Clone + Iterator<Item = &'c [u8]>>(
+        self,
+        ty: &crate::AlgebraicType,
+        total_bsatn_len: usize,
+        bsatn: I,
+    ) -> Result<Self::Ok, Self::Error> {
+        // SAFETY: Forward caller requirements of this method to that we are calling.
+        unsafe { self.fmt.serialize_bsatn_in_chunks(ty, total_bsatn_len, bsatn) }
+    }
+
+    unsafe fn serialize_str_in_chunks<'c, I: Clone + Iterator<Item = &'c [u8]>>(
+        self,
+        total_len: usize,
+        string: I,
+    ) -> Result<Self::Ok, Self::Error> {
+        // SAFETY: Forward caller requirements of this method to that we are calling.
+        unsafe { self.fmt.serialize_str_in_chunks(total_len, string) }
+    }
+}
+
+/// Renders `value` as a SQL literal, see [`SqlLiteralFormatter`].
+pub fn to_sql_literal<T: ser::Serialize + ?Sized>(value: &T) -> Result<String, SqlLiteralError> {
+    let mut out = String::new();
+    value.serialize(SqlLiteralFormatter::new(&mut out))?;
+    Ok(out)
+}
+
+/// An error occurred rendering a value as a SQL literal.
+#[derive(thiserror::Error, Debug)]
+pub enum SqlLiteralError {
+    /// The value has no SQL literal representation.
+    #[error("{0} cannot be rendered as a SQL literal")]
+    Unsupported(&'static str),
+    /// The value contains a NUL character, which SQL strings can't hold.
+    #[error("string contains a NUL character")]
+    NulInString,
+    /// Serializing the value failed.
+    #[error("{0}")]
+    Custom(String),
+    /// Writing to the output failed.
+    #[error(transparent)]
+    Fmt(#[from] fmt::Error),
+}
+
+impl ser::Error for SqlLiteralError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+/// An implementation of [`Serializer`](ser::Serializer) rendering scalar values
+/// as SQL literals, for use in generated queries.
+///
+/// Unlike the display oriented [`Satn::fmt_psql`], the output is always parseable SQL:
+///
+/// - strings are single quoted, with embedded quotes doubled, e.g. `'it''s'`
+/// - bytes are rendered as hex `bytea` literals, e.g. `'\x01ab'`
+/// - booleans are rendered as `TRUE` and `FALSE`
+/// - non-finite floats are rendered as the strings `'NaN'`, `'Infinity'` and `'-Infinity'`
+/// - `none` is rendered as `NULL`, and `some` as the literal of its value
+///
+/// Values which have no literal representation, i.e., arrays, products and
+/// other sums, are rejected with [`SqlLiteralError::Unsupported`],
+/// as are strings containing NUL.
+pub struct SqlLiteralFormatter<'a, W: ?Sized> {
+    out: &'a mut W,
+}
+
+impl<'a, W: fmt::Write + ?Sized> SqlLiteralFormatter<'a, W> {
+    /// Write the SQL literal to `out`.
+    pub fn new(out: &'a mut W) -> Self {
+        Self { out }
+    }
+
+    fn write_fmt(self, args: fmt::Arguments) -> Result<(), SqlLiteralError> {
+        self.out.write_fmt(args)?;
+        Ok(())
+    }
+}
+
+/// The compound serializers of [`SqlLiteralFormatter`], which can't be constructed.
+pub enum SqlLiteralUnsupported {}
+
+impl ser::SerializeArray for SqlLiteralUnsupported {
+    type Ok = ();
+    type Error = SqlLiteralError;
+
+    fn serialize_element<T: ser::Serialize + ?Sized>(&mut self, _: &T) -> Result<(), Self::Error> {
+        match *self {}
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        match self {}
+    }
+}
+
+impl ser::SerializeSeqProduct for SqlLiteralUnsupported {
+    type Ok = ();
+    type Error = SqlLiteralError;
+
+    fn serialize_element<T: ser::Serialize + ?Sized>(&mut self, _: &T) -> Result<(), Self::Error> {
+        match *self {}
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        match self {}
+    }
+}
+
+impl ser::SerializeNamedProduct for SqlLiteralUnsupported {
+    type Ok = ();
+    type Error = SqlLiteralError;
+
+    fn serialize_element<T: ser::Serialize + ?Sized>(&mut self, _: Option<&str>, _: &T) -> Result<(), Self::Error> {
+        match *self {}
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        match self {}
+    }
+}
+
+impl<W: fmt::Write + ?Sized> ser::Serializer for SqlLiteralFormatter<'_, W> {
+    type Ok = ();
+    type Error = SqlLiteralError;
+    type SerializeArray = SqlLiteralUnsupported;
+    type SerializeSeqProduct = SqlLiteralUnsupported;
+    type SerializeNamedProduct = SqlLiteralUnsupported;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        write!(self, "{}", if v { "TRUE" } else { "FALSE" })
+    }
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
         write!(self, "{v}")
     }
-    fn serialize_u32(mut self, v: u32) -> Result<Self::Ok, Self::Error> {
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
         write!(self, "{v}")
     }
-    fn serialize_u64(mut self, v: u64) -> Result<Self::Ok, Self::Error> {
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
         write!(self, "{v}")
     }
-    fn serialize_u128(mut self, v: u128) -> Result<Self::Ok, Self::Error> {
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
         write!(self, "{v}")
     }
-    fn serialize_u256(mut self, v: u256) -> Result<Self::Ok, Self::Error> {
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
         write!(self, "{v}")
     }
-    fn serialize_i8(mut self, v: i8) -> Result<Self::Ok, Self::Error> {
+    fn serialize_u256(self, v: u256) -> Result<Self::Ok, Self::Error> {
         write!(self, "{v}")
     }
-    fn serialize_i16(mut self, v: i16) -> Result<Self::Ok, Self::Error> {
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
         write!(self, "{v}")
     }
-    fn serialize_i32(mut self, v: i32) -> Result<Self::Ok, Self::Error> {
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
         write!(self, "{v}")
     }
-    fn serialize_i64(mut self, v: i64) -> Result<Self::Ok, Self::Error> {
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
         write!(self, "{v}")
     }
-    fn serialize_i128(mut self, v: i128) -> Result<Self::Ok, Self::Error> {
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
         write!(self, "{v}")
     }
-    fn serialize_i256(mut self, v: i256) -> Result<Self::Ok, Self::Error> {
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
         write!(self, "{v}")
     }
-    fn serialize_f32(mut self, v: f32) -> Result<Self::Ok, Self::Error> {
+    fn serialize_i256(self, v: i256) -> Result<Self::Ok, Self::Error> {
         write!(self, "{v}")
     }
-    fn serialize_f64(mut self, v: f64) -> Result<Self::Ok, Self::Error> {
-        write!(self, "{v}")
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        // Widening would add digits, e.g., `0.1` would become `0.10000000149011612`.
+        match v.is_finite() {
+            true => Ok(write_float(self.out, v)?),
+            false => self.serialize_f64(v.into()),
+        }
+    }
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        match v {
+            _ if v.is_nan() => write!(self, "'NaN'"),
+            f64::INFINITY => write!(self, "'Infinity'"),
+            f64::NEG_INFINITY => write!(self, "'-Infinity'"),
+            _ => Ok(write_float(self.out, v)?),
+        }
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        if v.contains('\0') {
+            return Err(SqlLiteralError::NulInString);
+        }
+        self.out.write_char('\'')?;
+        for part in v.split_inclusive('\'') {
+            self.out.write_str(part)?;
+            if part.ends_with('\'') {
+                self.out.write_char('\'')?;
+            }
+        }
+        self.out.write_char('\'')?;
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        write!(self, "'\\x{}'", hex::encode(v))
+    }
+
+    fn serialize_array(self, _len: usize) -> Result<Self::SerializeArray, Self::Error> {
+        Err(SqlLiteralError::Unsupported("an array"))
+    }
+
+    fn serialize_seq_product(self, _len: usize) -> Result<Self::SerializeSeqProduct, Self::Error> {
+        Err(SqlLiteralError::Unsupported("a product"))
+    }
+
+    fn serialize_named_product(self, _len: usize) -> Result<Self::SerializeNamedProduct, Self::Error> {
+        Err(SqlLiteralError::Unsupported("a product"))
+    }
+
+    fn serialize_variant<T: ser::Serialize + ?Sized>(
+        self,
+        _tag: u8,
+        name: Option<&str>,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        match name {
+            Some(OPTION_NONE_TAG) => write!(self, "NULL"),
+            Some(OPTION_SOME_TAG) => value.serialize(self),
+            _ => Err(SqlLiteralError::Unsupported("a sum value")),
+        }
+    }
+
+    unsafe fn serialize_bsatn(self, ty: &crate::AlgebraicType, bsatn: &[u8]) -> Result<Self::Ok, Self::Error> {
+        // SAFETY: Forward caller requirements of this method to that we are calling.
+        let res = unsafe { ValueSerializer.serialize_bsatn(ty, bsatn) };
+        let value = res.unwrap_or_else(|x| match x {});
+        // Serialize with the names of variants, so options are recognized.
+        WithTypespace::empty(ty).with_value(&value).serialize(self)
+    }
+
+    unsafe fn serialize_bsatn_in_chunks<'c, I: Clone + Iterator<Item = &'c [u8]>>(
+        self,
+        ty: &crate::AlgebraicType,
+        total_bsatn_len: usize,
+        bsatn: I,
+    ) -> Result<Self::Ok, Self::Error> {
+        // SAFETY: Forward caller requirements of this method to that we are calling.
+        let res = unsafe { ValueSerializer.serialize_bsatn_in_chunks(ty, total_bsatn_len, bsatn) };
+        let value = res.unwrap_or_else(|x| match x {});
+        WithTypespace::empty(ty).with_value(&value).serialize(self)
+    }
+
+    unsafe fn serialize_str_in_chunks<'c, I: Clone + Iterator<Item = &'c [u8]>>(
+        self,
+        total_len: usize,
+        string: I,
+    ) -> Result<Self::Ok, Self::Error> {
+        // SAFETY: Forward caller requirements of this method to that we are calling.
+        let res = unsafe { ValueSerializer.serialize_str_in_chunks(total_len, string) };
+        let value = res.unwrap_or_else(|x| match x {});
+        value.serialize(self)
+    }
+}
+
+/// An event in the structure of a value, as yielded by [`SatnTokenizer`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum SatnEvent {
+    /// The start of a product, followed by a [`SatnEvent::Field`] and the
+    /// events of its value for each field.
+    BeginProduct,
+    /// The next field of the current product, with its name, if known.
+    Field(Option<Box<str>>),
+    /// The end of the current product.
+    EndProduct,
+    /// The start of an array, followed by the events of its elements.
+    BeginArray,
+    /// The end of the current array.
+    EndArray,
+    /// The start of a sum value, followed by the events of its payload.
+    BeginVariant {
+        /// The tag of the variant.
+        tag: u8,
+        /// The name of the variant, if known.
+        name: Option<Box<str>>,
+    },
+    /// The end of the current sum value.
+    EndVariant,
+    /// A scalar value, i.e., a bool, number, string or byte array.
+    Scalar(AlgebraicValue),
+}
+
+/// Collects the structure of a value into a sequence of [`SatnEvent`]s,
+/// like a SAX parser does for a document, and yields them.
+///
+/// This is a lower-level counterpart to the SATN formatters,
+/// allowing consumers to build their own output, or to transform it.
+/// For example, the value `(a = 1, b = [2])` yields
+/// `BeginProduct`, `Field(Some("a"))`, `Scalar(U32(1))`, `Field(Some("b"))`,
+/// `BeginArray`, `Scalar(U32(2))`, `EndArray` and `EndProduct`.
+///
+/// This is an eager event collector, not a streaming interface:
+/// serialization pushes into the serializer and can't be suspended,
+/// so all events, including an owned copy of each scalar,
+/// are collected into memory when the tokenizer is created.
+/// Consumers that must not buffer the whole value should implement
+/// [`Serializer`](ser::Serializer) themselves instead.
+pub struct SatnTokenizer {
+    events: std::vec::IntoIter<SatnEvent>,
+}
+
+impl SatnTokenizer {
+    /// Tokenize `value`.
+    pub fn new<T: ser::Serialize + ?Sized>(value: &T) -> Self {
+        let mut events = Vec::new();
+        let res = value.serialize(EventSerializer { events: &mut events });
+        res.unwrap_or_else(|x| match x {});
+        Self {
+            events: events.into_iter(),
+        }
+    }
+}
+
+impl Iterator for SatnTokenizer {
+    type Item = SatnEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.events.size_hint()
+    }
+}
+
+/// An implementation of [`Serializer`](ser::Serializer) collecting [`SatnEvent`]s,
+/// see [`SatnTokenizer`].
+struct EventSerializer<'a> {
+    events: &'a mut Vec<SatnEvent>,
+}
+
+impl EventSerializer<'_> {
+    fn scalar(self, value: Result<AlgebraicValue, Infallible>) -> Result<(), Infallible> {
+        self.events.push(SatnEvent::Scalar(value?));
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for EventSerializer<'a> {
+    type Ok = ();
+    type Error = Infallible;
+    type SerializeArray = Self;
+    type SerializeSeqProduct = Self;
+    type SerializeNamedProduct = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_bool(v))
+    }
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_u8(v))
+    }
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_u16(v))
+    }
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_u32(v))
+    }
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_u64(v))
+    }
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_u128(v))
+    }
+    fn serialize_u256(self, v: u256) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_u256(v))
+    }
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_i8(v))
+    }
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_i16(v))
+    }
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_i32(v))
+    }
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_i64(v))
+    }
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_i128(v))
+    }
+    fn serialize_i256(self, v: i256) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_i256(v))
+    }
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_f32(v))
     }
-
-    fn serialize_str(mut self, v: &str) -> Result<Self::Ok, Self::Error> {
-        write!(self, "\"{}\"", v)
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_f64(v))
     }
-
-    fn serialize_bytes(mut self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
-        write!(self, "0x{}", hex::encode(v))
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_str(v))
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        self.scalar(ValueSerializer.serialize_bytes(v))
     }
 
-    fn serialize_array(mut self, _len: usize) -> Result<Self::SerializeArray, Self::Error> {
-        write!(self, "[")?; // Closed via `.end()`.
-        Ok(ArrayFormatter {
-            f: EntryWrapper::new(self.f),
-        })
+    fn serialize_array(self, _len: usize) -> Result<Self::SerializeArray, Self::Error> {
+        self.events.push(SatnEvent::BeginArray);
+        Ok(self)
     }
 
-    fn serialize_seq_product(self, len: usize) -> Result<Self::SerializeSeqProduct, Self::Error> {
-        // Delegate to named products handling of element formatting.
-        self.serialize_named_product(len).map(|inner| SeqFormatter { inner })
+    fn serialize_seq_product(self, _len: usize) -> Result<Self::SerializeSeqProduct, Self::Error> {
+        self.events.push(SatnEvent::BeginProduct);
+        Ok(self)
     }
 
-    fn serialize_named_product(mut self, _len: usize) -> Result<Self::SerializeNamedProduct, Self::Error> {
-        write!(self, "(")?; // Closed via `.end()`.
-        Ok(NamedFormatter {
-            f: EntryWrapper::new(self.f),
-            idx: 0,
-        })
+    fn serialize_named_product(self, _len: usize) -> Result<Self::SerializeNamedProduct, Self::Error> {
+        self.events.push(SatnEvent::BeginProduct);
+        Ok(self)
     }
 
     fn serialize_variant<T: ser::Serialize + ?Sized>(
-        mut self,
-        _tag: u8,
+        self,
+        tag: u8,
         name: Option<&str>,
         value: &T,
     ) -> Result<Self::Ok, Self::Error> {
-        write!(self, "(")?;
-        EntryWrapper::<','>::new(self.f.as_mut()).entry(|mut f| {
-            if let Some(name) = name {
-                write!(f, "{}", name)?;
-            }
-            write!(f, " = ")?;
-            value.serialize(SatnFormatter { f })?;
-            Ok(())
-        })?;
-        write!(self, ")")
+        self.events.push(SatnEvent::BeginVariant {
+            tag,
+            name: name.map(Into::into),
+        });
+        value.serialize(EventSerializer { events: self.events })?;
+        self.events.push(SatnEvent::EndVariant);
+        Ok(())
     }
 
     unsafe fn serialize_bsatn(self, ty: &crate::AlgebraicType, bsatn: &[u8]) -> Result<Self::Ok, Self::Error> {
-        // TODO(Centril): Consider instead deserializing the `bsatn` through a
-        // deserializer that serializes into `self` directly.
-
-        // First convert the BSATN to an `AlgebraicValue`.
         // SAFETY: Forward caller requirements of this method to that we are calling.
-        let res = unsafe { ValueSerializer.serialize_bsatn(ty, bsatn) };
-        let value = res.unwrap_or_else(|x| match x {});
-
-        // Then serialize that.
-        value.serialize(self)
+        let value = unsafe { ValueSerializer.serialize_bsatn(ty, bsatn) }?;
+        // Serialize with the type, so the names of fields and variants are known.
+        WithTypespace::empty(ty).with_value(&value).serialize(self)
     }
 
     unsafe fn serialize_bsatn_in_chunks<'c, I: Clone + Iterator<Item = &'c [u8]>>(
@@ -367,17 +2652,9 @@ impl<'a, 'f> ser::Serializer for SatnFormatter<'a, 'f> {
         total_bsatn_len: usize,
         bsatn: I,
     ) -> Result<Self::Ok, Self::Error> {
-        // TODO(Centril): Unlike above, in this case we must at minimum concatenate `bsatn`
-        // before we can do the piping mentioned above, but that's better than
-        // serializing to `AlgebraicValue` first, so consider that.
-
-        // First convert the BSATN to an `AlgebraicValue`.
         // SAFETY: Forward caller requirements of this method to that we are calling.
-        let res = unsafe { ValueSerializer.serialize_bsatn_in_chunks(ty, total_bsatn_len, bsatn) };
-        let value = res.unwrap_or_else(|x| match x {});
-
-        // Then serialize that.
-        value.serialize(self)
+        let value = unsafe { ValueSerializer.serialize_bsatn_in_chunks(ty, total_bsatn_len, bsatn) }?;
+        WithTypespace::empty(ty).with_value(&value).serialize(self)
     }
 
     unsafe fn serialize_str_in_chunks<'c, I: Clone + Iterator<Item = &'c [u8]>>(
@@ -385,294 +2662,1557 @@ impl<'a, 'f> ser::Serializer for SatnFormatter<'a, 'f> {
         total_len: usize,
         string: I,
     ) -> Result<Self::Ok, Self::Error> {
-        // First convert the `string` to an `AlgebraicValue`.
         // SAFETY: Forward caller requirements of this method to that we are calling.
-        let res = unsafe { ValueSerializer.serialize_str_in_chunks(total_len, string) };
-        let value = res.unwrap_or_else(|x| match x {});
-
-        // Then serialize that.
-        // This incurs a very minor cost of branching on `AlgebraicValue::String`.
-        value.serialize(self)
+        let value = unsafe { ValueSerializer.serialize_str_in_chunks(total_len, string) };
+        self.scalar(value)
     }
 }
 
-/// Defines the SATN formatting for arrays.
-struct ArrayFormatter<'a, 'f> {
-    /// The formatter for each element separating elements by a `,`.
-    f: EntryWrapper<'a, 'f, ','>,
-}
-
-impl ser::SerializeArray for ArrayFormatter<'_, '_> {
+impl ser::SerializeArray for EventSerializer<'_> {
     type Ok = ();
-    type Error = SatnError;
+    type Error = Infallible;
 
     fn serialize_element<T: ser::Serialize + ?Sized>(&mut self, elem: &T) -> Result<(), Self::Error> {
-        self.f.entry(|f| elem.serialize(SatnFormatter { f }).map_err(|e| e.0))?;
-        Ok(())
+        elem.serialize(EventSerializer { events: self.events })
     }
 
-    fn end(mut self) -> Result<Self::Ok, Self::Error> {
-        write!(self.f.fmt, "]")?;
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.events.push(SatnEvent::EndArray);
         Ok(())
     }
 }
 
-/// Provides the data format for unnamed products for SATN.
-struct SeqFormatter<'a, 'f> {
-    /// Delegates to the named format.
-    inner: NamedFormatter<'a, 'f>,
-}
-
-impl ser::SerializeSeqProduct for SeqFormatter<'_, '_> {
+impl ser::SerializeSeqProduct for EventSerializer<'_> {
     type Ok = ();
-    type Error = SatnError;
+    type Error = Infallible;
 
     fn serialize_element<T: ser::Serialize + ?Sized>(&mut self, elem: &T) -> Result<(), Self::Error> {
-        ser::SerializeNamedProduct::serialize_element(&mut self.inner, None, elem)
+        ser::SerializeNamedProduct::serialize_element(self, None, elem)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        ser::SerializeNamedProduct::end(self.inner)
+        ser::SerializeNamedProduct::end(self)
     }
 }
 
-/// Provides the data format for named products for SATN.
-struct NamedFormatter<'a, 'f> {
-    /// The formatter for each element separating elements by a `,`.
-    f: EntryWrapper<'a, 'f, ','>,
-    /// The index of the element.
-    idx: usize,
-}
-
-impl ser::SerializeNamedProduct for NamedFormatter<'_, '_> {
+impl ser::SerializeNamedProduct for EventSerializer<'_> {
     type Ok = ();
-    type Error = SatnError;
+    type Error = Infallible;
 
     fn serialize_element<T: ser::Serialize + ?Sized>(
         &mut self,
         name: Option<&str>,
         elem: &T,
     ) -> Result<(), Self::Error> {
-        let res = self.f.entry(|mut f| {
-            // Format the name or use the index if unnamed.
-            if let Some(name) = name {
-                write!(f, "{}", name)?;
-            } else {
-                write!(f, "{}", self.idx)?;
-            }
-            write!(f, " = ")?;
-            elem.serialize(SatnFormatter { f })?;
-            Ok(())
-        });
-        self.idx += 1;
-        res?;
-        Ok(())
+        self.events.push(SatnEvent::Field(name.map(Into::into)));
+        elem.serialize(EventSerializer { events: self.events })
     }
 
-    fn end(mut self) -> Result<Self::Ok, Self::Error> {
-        write!(self.f.fmt, ")")?;
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.events.push(SatnEvent::EndProduct);
         Ok(())
     }
-}
+}
+
+/// The marker rendered by [`delta`] in place of a part equal to the base value.
+const UNCHANGED: &str = "<unchanged>";
+
+/// Formats `new` using the SATN data format relative to `base`,
+/// rendering each field, array element and variant payload which is equal
+/// to its counterpart in `base` as `<unchanged>`.
+///
+/// The values are walked in lockstep using [`SatnTokenizer`].
+/// Where the structure of the values differs,
+/// e.g. for products with different fields or sums with different variants,
+/// the part of `new` is rendered in full.
+/// Arrays, other than byte arrays, are compared per index,
+/// elements beyond the length of `base`
+/// are rendered in full. Elements removed from the end are not rendered.
+///
+/// The output is always compact, and not affected by [`Options`].
+pub fn delta<B: ser::Serialize + ?Sized, N: ser::Serialize + ?Sized>(base: &B, new: &N) -> String {
+    delta_with(base, new, false)
+}
+
+/// Like [`delta`], but omits unchanged parts entirely if `omit_unchanged` is set.
+///
+/// To retain their position, array elements are then prefixed with their index,
+/// as in `[[1]: 5]`.
+pub fn delta_with<B: ser::Serialize + ?Sized, N: ser::Serialize + ?Sized>(
+    base: &B,
+    new: &N,
+    omit_unchanged: bool,
+) -> String {
+    let base = DeltaNode::parse(&mut SatnTokenizer::new(base).peekable());
+    let new = DeltaNode::parse(&mut SatnTokenizer::new(new).peekable());
+    let mut out = String::new();
+    new.write_delta(&mut out, &base, omit_unchanged);
+    out
+}
+
+/// The tree of a value, as built from its [`SatnEvent`]s, used by [`delta`].
+#[derive(PartialEq)]
+enum DeltaNode {
+    Product(Vec<(Option<Box<str>>, DeltaNode)>),
+    Array(Vec<DeltaNode>),
+    Variant {
+        tag: u8,
+        name: Option<Box<str>>,
+        payload: Box<DeltaNode>,
+    },
+    Scalar(AlgebraicValue),
+}
+
+impl DeltaNode {
+    /// Builds the tree of the next value in `events`.
+    fn parse(events: &mut iter::Peekable<SatnTokenizer>) -> Self {
+        match events.next() {
+            Some(SatnEvent::BeginProduct) => {
+                let mut fields = Vec::new();
+                while let Some(SatnEvent::Field(name)) = events.next() {
+                    fields.push((name, Self::parse(events)));
+                }
+                Self::Product(fields)
+            }
+            Some(SatnEvent::BeginArray) => {
+                let mut elems = Vec::new();
+                while events.next_if_eq(&SatnEvent::EndArray).is_none() {
+                    elems.push(Self::parse(events));
+                }
+                Self::Array(elems)
+            }
+            Some(SatnEvent::BeginVariant { tag, name }) => {
+                let payload = Box::new(Self::parse(events));
+                events.next_if_eq(&SatnEvent::EndVariant);
+                Self::Variant { tag, name, payload }
+            }
+            Some(SatnEvent::Scalar(value)) => Self::Scalar(value),
+            event => unreachable!("unexpected event {event:?} at the start of a value"),
+        }
+    }
+
+    /// Renders `self` in full.
+    fn write(&self, out: &mut String) {
+        match self {
+            Self::Product(fields) => {
+                out.push('(');
+                for (idx, (name, field)) in fields.iter().enumerate() {
+                    Self::write_field_name(out, idx > 0, idx, name.as_deref());
+                    field.write(out);
+                }
+                out.push(')');
+            }
+            Self::Array(elems) => {
+                out.push('[');
+                for (idx, elem) in elems.iter().enumerate() {
+                    if idx > 0 {
+                        out.push_str(", ");
+                    }
+                    elem.write(out);
+                }
+                out.push(']');
+            }
+            Self::Variant { name, payload, .. } => {
+                let _ = write!(out, "({} = ", name.as_deref().unwrap_or_default());
+                payload.write(out);
+                out.push(')');
+            }
+            Self::Scalar(value) => out.push_str(&value.to_satn()),
+        }
+    }
+
+    /// Renders `self` relative to `base`.
+    fn write_delta(&self, out: &mut String, base: &Self, omit_unchanged: bool) {
+        if self == base {
+            out.push_str(UNCHANGED);
+            return;
+        }
+        match (base, self) {
+            (Self::Product(base_fields), Self::Product(fields))
+                if base_fields.len() == fields.len()
+                    && iter::zip(base_fields, fields).all(|((a, _), (b, _))| a == b) =>
+            {
+                out.push('(');
+                let mut first = true;
+                for (idx, ((_, base), (name, field))) in iter::zip(base_fields, fields).enumerate() {
+                    if omit_unchanged && field == base {
+                        continue;
+                    }
+                    Self::write_field_name(out, !mem::take(&mut first), idx, name.as_deref());
+                    field.write_delta(out, base, omit_unchanged);
+                }
+                out.push(')');
+            }
+            (Self::Array(base_elems), Self::Array(elems)) => {
+                out.push('[');
+                let mut first = true;
+                for (idx, elem) in elems.iter().enumerate() {
+                    let base = base_elems.get(idx);
+                    if omit_unchanged && base == Some(elem) {
+                        continue;
+                    }
+                    if !mem::take(&mut first) {
+                        out.push_str(", ");
+                    }
+                    if omit_unchanged {
+                        let _ = write!(out, "[{idx}]: ");
+                    }
+                    match base {
+                        Some(base) => elem.write_delta(out, base, omit_unchanged),
+                        None => elem.write(out),
+                    }
+                }
+                out.push(']');
+            }
+            (
+                Self::Variant {
+                    tag: base_tag,
+                    payload: base,
+                    ..
+                },
+                Self::Variant { tag, name, payload },
+            ) if base_tag == tag => {
+                let _ = write!(out, "({} = ", name.as_deref().unwrap_or_default());
+                payload.write_delta(out, base, omit_unchanged);
+                out.push(')');
+            }
+            _ => self.write(out),
+        }
+    }
+
+    /// Renders the name of the field at `idx`, preceded by a separator if `sep` is set.
+    fn write_field_name(out: &mut String, sep: bool, idx: usize, name: Option<&str>) {
+        if sep {
+            out.push_str(", ");
+        }
+        let _ = match name {
+            Some(name) => write!(out, "{name} = "),
+            None => write!(out, "{idx} = "),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{product, AlgebraicType, AlgebraicValue, ProductValue, WithTypespace};
+    use std::collections::{BTreeMap, HashMap};
+    use std::rc::Rc;
+
+    #[test]
+    fn sorted_map_is_deterministic() {
+        let keys = ["delta", "alpha", "charlie", "bravo"];
+        let a = keys
+            .iter()
+            .map(|k| (k.to_string(), k.len() as u32))
+            .collect::<HashMap<_, _>>();
+        let b = keys
+            .iter()
+            .rev()
+            .map(|k| (k.to_string(), k.len() as u32))
+            .collect::<HashMap<_, _>>();
+
+        let rendered = SortedMap::new(&a).to_satn();
+        assert_eq!(rendered, SortedMap::new(&a).to_satn());
+        assert_eq!(rendered, SortedMap::new(&b).to_satn());
+        assert_eq!(
+            rendered,
+            r#"[(key = "alpha", value = 5), (key = "bravo", value = 5), (key = "charlie", value = 7), (key = "delta", value = 5)]"#
+        );
+    }
+
+    #[test]
+    fn pretty_crlf_newlines() {
+        let map = BTreeMap::from([("a".to_string(), vec![1u32, 2]), ("b".to_string(), vec![3])]);
+        let opts = Options {
+            newline: "\r\n",
+            ..Options::default()
+        };
+
+        let out = SortedMap::new(&map).to_satn_pretty_with(&opts);
+        let lines = out.split_inclusive('\n').collect::<Vec<_>>();
+        assert!(lines.len() > 1);
+        for line in &lines[..lines.len() - 1] {
+            assert!(line.ends_with("\r\n"), "line {line:?} in {out:?}");
+        }
+        assert!(!lines[lines.len() - 1].ends_with('\r'));
+        assert_eq!(out.replace("\r\n", "\n"), SortedMap::new(&map).to_satn_pretty());
+    }
+
+    #[test]
+    fn max_total_entries_budget() {
+        let opts = Options {
+            max_total_entries: Some(3),
+            ..Options::default()
+        };
+
+        let wide = (0..50_000).map(AlgebraicValue::U32).collect::<ProductValue>();
+        assert_eq!(wide.to_satn_with(&opts), "(0 = 0, 1 = 1, 2 = 2, ...)");
+
+        let deep = (0..100).fold(AlgebraicValue::U32(0), |inner, _| product![inner].into());
+        assert_eq!(deep.to_satn_with(&opts), "(0 = (0 = (0 = (...))))");
+
+        let pretty = wide.to_satn_pretty_with(&opts);
+        assert_eq!(pretty.lines().filter(|line| line.contains(TRUNCATED)).count(), 1);
+    }
+
+    #[test]
+    fn bool_styles() {
+        let numeric = Options {
+            bool_style: BoolStyle::Numeric,
+            ..Options::default()
+        };
+
+        assert_eq!(true.to_satn(), "true");
+        assert_eq!(false.to_satn(), "false");
+        assert_eq!(true.to_satn_with(&numeric), "1");
+        assert_eq!(false.to_satn_with(&numeric), "0");
+        assert_eq!(
+            product![AlgebraicValue::Bool(true), AlgebraicValue::Bool(false)].to_satn_with(&numeric),
+            "(0 = 1, 1 = 0)"
+        );
+    }
+
+    #[test]
+    fn diff_marks_changed_field() {
+        let a = product![AlgebraicValue::U32(1), AlgebraicValue::U32(2), AlgebraicValue::U32(3)];
+        let b = product![AlgebraicValue::U32(1), AlgebraicValue::U32(42), AlgebraicValue::U32(3)];
+
+        let expected = [" (", "     0 = 1,", "-    1 = 2,", "+    1 = 42,", "     2 = 3,", " )"];
+        assert_eq!(diff(&a, &b).lines().collect::<Vec<_>>(), expected);
+        assert!(diff(&a, &a).lines().all(|line| line.starts_with(' ')));
+    }
+
+    #[test]
+    fn diff_keeps_longest_common_subsequence() {
+        // Deterministic pseudo-random arrays with many repeated elements.
+        let mut seed = 42u32;
+        let mut array = |len| {
+            (0..len)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                    (seed >> 16) % 5
+                })
+                .collect::<Vec<u32>>()
+        };
+        for (len_a, len_b) in [(0, 3), (1, 1), (7, 5), (40, 60), (200, 150)] {
+            let (a, b) = (array(len_a), array(len_b));
+            let out = diff(&a, &b);
+            let lines = |keep: char| {
+                out.lines()
+                    .filter(|line| !line.starts_with(keep))
+                    .map(|line| &line[1..])
+                    .collect::<Vec<_>>()
+            };
+            // Both renderings can be recovered.
+            assert_eq!(lines('+').join("\n"), a.to_satn_pretty());
+            assert_eq!(lines('-').join("\n"), b.to_satn_pretty());
+
+            // The unchanged lines are a longest common subsequence.
+            let (a, b) = (a.to_satn_pretty(), b.to_satn_pretty());
+            let (a, b) = (a.lines().collect::<Vec<_>>(), b.lines().collect::<Vec<_>>());
+            let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+            for i in 0..a.len() {
+                for j in 0..b.len() {
+                    lcs[i + 1][j + 1] = if a[i] == b[j] {
+                        lcs[i][j] + 1
+                    } else {
+                        lcs[i][j + 1].max(lcs[i + 1][j])
+                    };
+                }
+            }
+            let unchanged = out.lines().filter(|line| line.starts_with(' ')).count();
+            assert_eq!(unchanged, lcs[a.len()][b.len()]);
+        }
+    }
+
+    #[test]
+    fn annotated_field() {
+        let map = BTreeMap::from([("a".to_string(), 1u32)]);
+        let opts = Options {
+            annotate: Some(|name| (name == "value").then(|| "years".to_string())),
+            ..Options::default()
+        };
+        let value = SortedMap::new(&map);
+
+        let out = value.to_satn_pretty_with(&opts);
+        let lines = out.lines().filter(|line| line.contains("/*")).collect::<Vec<_>>();
+        assert_eq!(lines, ["        value = 1 /* years */,"]);
+        // Comments are only emitted in pretty mode.
+        assert_eq!(value.to_satn_with(&opts), value.to_satn());
+    }
+
+    #[test]
+    fn pretty_metrics_match_rendering() {
+        let map = BTreeMap::from([("a".to_string(), vec![1u32, 2]), ("bravo".to_string(), vec![])]);
+        let values = [
+            AlgebraicValue::U32(42),
+            product![AlgebraicValue::U32(1), AlgebraicValue::String("hello".into())].into(),
+        ];
+
+        let renderings = values
+            .iter()
+            .map(|value| (value.pretty_metrics(), value.to_satn_pretty()))
+            .chain([(
+                SortedMap::new(&map).pretty_metrics(),
+                SortedMap::new(&map).to_satn_pretty(),
+            )]);
+        for (metrics, pretty) in renderings {
+            let expected = PrettyMetrics {
+                lines: pretty.lines().count(),
+                max_width: pretty.lines().map(|line| line.chars().count()).max().unwrap_or(0),
+            };
+            assert_eq!(metrics, expected, "{pretty}");
+        }
+    }
+
+    #[test]
+    fn unnamed_styles() {
+        let value = product![
+            AlgebraicValue::U32(1),
+            AlgebraicValue::String("two".into()),
+            AlgebraicValue::Bool(true)
+        ];
+        let positional = Options {
+            unnamed_style: UnnamedStyle::Positional,
+            ..Options::default()
+        };
+
+        assert_eq!(value.to_satn(), r#"(0 = 1, 1 = "two", 2 = true)"#);
+        assert_eq!(value.to_satn_with(&positional), r#"(1, "two", true)"#);
+        assert_eq!(
+            value.to_satn_pretty_with(&positional),
+            "(\n    1,\n    \"two\",\n    true,\n)"
+        );
+    }
+
+    #[test]
+    fn bytes_styles() {
+        let text = Options {
+            bytes_style: BytesStyle::TextOrHex,
+            ..Options::default()
+        };
+        let ascii = AlgebraicValue::Bytes(b"say \"hi\"\n".as_slice().into());
+        let utf8 = AlgebraicValue::Bytes("grüß".as_bytes().into());
+        let binary = AlgebraicValue::Bytes([0xde, 0xad, 0xbe, 0xef].as_slice().into());
+        let control = AlgebraicValue::Bytes([0, 1, 2].as_slice().into());
+
+        assert_eq!(ascii.to_satn(), "0x73617920226869220a");
+        assert_eq!(ascii.to_satn_with(&text), r#"t"say \"hi\"\n""#);
+        let whitespace = AlgebraicValue::Bytes(b"it's\t\x0c".as_slice().into());
+        assert_eq!(whitespace.to_satn_with(&text), r#"t"it's\t\u{c}""#);
+        assert_eq!(utf8.to_satn_with(&text), r#"t"grüß""#);
+        assert_eq!(binary.to_satn_with(&text), "0xdeadbeef");
+        assert_eq!(control.to_satn_with(&text), "0x000102");
+    }
+
+    #[test]
+    fn variant_styles() {
+        let tag = Options {
+            variant_style: VariantStyle::Tag,
+            ..Options::default()
+        };
+        let ty = AlgebraicType::option(AlgebraicType::U32);
+        let some = AlgebraicValue::OptionSome(AlgebraicValue::U32(5));
+        let named = WithTypespace::empty(&ty).with_value(&some);
+        let unnamed = AlgebraicValue::sum(3, AlgebraicValue::unit());
+
+        assert_eq!(named.to_satn(), "(some = 5)");
+        assert_eq!(named.to_satn_with(&tag), "@0(5)");
+        assert_eq!(unnamed.to_satn_with(&tag), "@3(())");
+        assert_eq!(
+            product![unnamed.clone(), AlgebraicValue::sum(1, AlgebraicValue::U8(2))].to_satn_with(&tag),
+            "(0 = @3(()), 1 = @1(2))"
+        );
+    }
+
+    #[test]
+    fn byte_arrays_are_hex() {
+        // Arrays of `u8`, whether fixed-size or not, are serialized as bytes.
+        assert_eq!([0xde_u8, 0xad, 0xbe, 0xef].to_satn(), "0xdeadbeef");
+        assert_eq!(vec![1_u8, 2].to_satn(), "0x0102");
+        assert_eq!(vec![-1_i32, 2].to_satn(), "[-1, 2]");
+        assert_eq!([-1_i32, 2].to_satn(), "[-1, 2]");
+    }
+
+    #[test]
+    fn canonical_sorts_named_fields() {
+        let canonical = Options {
+            canonical: true,
+            ..Options::default()
+        };
+        let map = BTreeMap::from([("x".to_string(), vec![1u32, 2])]);
+        let a = SortedMap::new(&map);
+        let b = ReversedMap(&map);
+
+        assert_ne!(a.to_satn(), b.to_satn());
+        assert_eq!(a.to_satn_with(&canonical), b.to_satn_with(&canonical));
+        assert_eq!(a.to_satn_with(&canonical), "[(key = \"x\", value = [1, 2])]");
+        assert_eq!(a.to_satn_pretty_with(&canonical), b.to_satn_pretty_with(&canonical));
+        assert_eq!(a.to_satn_pretty_with(&canonical), a.to_satn_pretty());
+
+        // Unnamed fields keep their positional order.
+        let unnamed = product![AlgebraicValue::U32(2), AlgebraicValue::U32(1)];
+        assert_eq!(unnamed.to_satn_with(&canonical), unnamed.to_satn());
+    }
+
+    #[test]
+    fn redacted_fields() {
+        let ty = AlgebraicType::product([
+            ("name", AlgebraicType::String),
+            ("email", AlgebraicType::String),
+            (
+                "contact",
+                AlgebraicType::product([("ssn", AlgebraicType::String), ("city", AlgebraicType::String)]),
+            ),
+        ]);
+        let value = AlgebraicValue::product([
+            AlgebraicValue::String("Ada".into()),
+            AlgebraicValue::String("ada@example.com".into()),
+            AlgebraicValue::product([
+                AlgebraicValue::String("123-45-6789".into()),
+                AlgebraicValue::String("London".into()),
+            ]),
+        ]);
+        let typed = WithTypespace::empty(&ty).with_value(&value);
+        let opts = Options {
+            redact_fields: &["email", "ssn"],
+            ..Options::default()
+        };
+
+        assert_eq!(
+            typed.to_satn_with(&opts),
+            r#"(name = "Ada", email = <redacted>, contact = (ssn = <redacted>, city = "London"))"#
+        );
+        let pretty = typed.to_satn_pretty_with(&opts);
+        assert!(!pretty.contains("example.com") && !pretty.contains("6789"));
+        assert!(pretty.contains("email = <redacted>"));
+
+        // The row-logging entry points honour the redactions as well.
+        let mut out = Vec::new();
+        write_ndsatn_with([typed], &opts, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), typed.to_satn_with(&opts) + "\n");
+        let row = value.into_product().unwrap();
+        let mut out = Vec::new();
+        write_columnar_satn_with(ty.as_product().unwrap(), [&row], &opts, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "(\"name\", \"email\", \"contact\")\n(\"Ada\", <redacted>, (ssn = <redacted>, city = \"London\"))\n"
+        );
+    }
+
+    #[test]
+    fn pinned_field_order() {
+        let ty = ProductType::from([
+            ("a", AlgebraicType::U32),
+            ("b", AlgebraicType::U32),
+            ("c", AlgebraicType::U32),
+            ("d", AlgebraicType::U32),
+        ]);
+        let value = product![1u32, 2u32, 3u32, 4u32];
+        let value = WithTypespace::empty(&ty).with_value(&value);
+        let mut pinned = Options {
+            field_order: &["c", "a", "missing"],
+            ..Options::default()
+        };
+        assert_eq!(value.to_satn_with(&pinned), "(c = 3, a = 1, b = 2, d = 4)");
+        pinned.omit_unlisted_fields = true;
+        assert_eq!(value.to_satn_with(&pinned), "(c = 3, a = 1)");
+
+        // The order doesn't depend on the order of serialization.
+        let map = BTreeMap::from([("x".to_string(), vec![1u32, 2])]);
+        let pinned = Options {
+            field_order: &["value", "key"],
+            ..Options::default()
+        };
+        let expected = "[(value = [1, 2], key = \"x\")]";
+        assert_eq!(SortedMap::new(&map).to_satn_with(&pinned), expected);
+        assert_eq!(ReversedMap(&map).to_satn_with(&pinned), expected);
+    }
+
+    /// A test-only map rendering its entries with the value before the key.
+    struct ReversedMap<'a>(&'a BTreeMap<String, Vec<u32>>);
+
+    impl Serialize for ReversedMap<'_> {
+        fn serialize<S: ser::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+            use ser::{SerializeArray as _, SerializeNamedProduct as _};
+
+            struct Entry<'a>(&'a String, &'a Vec<u32>);
+            impl Serialize for Entry<'_> {
+                fn serialize<S: ser::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+                    let mut prod = ser.serialize_named_product(2)?;
+                    prod.serialize_element(Some("value"), self.1)?;
+                    prod.serialize_element(Some("key"), self.0)?;
+                    prod.end()
+                }
+            }
+
+            let mut arr = ser.serialize_array(self.0.len())?;
+            for (k, v) in self.0 {
+                arr.serialize_element(&Entry(k, v))?;
+            }
+            arr.end()
+        }
+    }
+
+    #[test]
+    fn ring_writer_retains_tail() {
+        let value = (0..1000)
+            .map(|i| AlgebraicValue::String(format!("ä{i}").into()))
+            .collect::<ProductValue>();
+        let full = value.to_satn();
+
+        for cap in [0, 1, 7, 100, 101, full.len() + 1] {
+            let mut ring = RingWriter::new(cap);
+            write!(ring, "{}", Wrapper::from_ref(&value)).unwrap();
+            let tail = ring.tail();
+            assert!(tail.len() <= cap);
+            // `ä` is two bytes long, so at most one byte is lost to a `char` boundary.
+            assert!(tail.len() + 1 >= cap.min(full.len()), "cap {cap}");
+            assert!(full.ends_with(tail));
+        }
+    }
+
+    #[test]
+    fn percent_encoded_roundtrip() {
+        let value = product![
+            AlgebraicValue::String("say \"hi\" & 50% off".into()),
+            AlgebraicValue::U32(7)
+        ];
+        let satn = value.to_satn();
+
+        let mut enc = PercentEncoder(String::new());
+        write!(enc, "{}", Wrapper::from_ref(&value)).unwrap();
+        let encoded = enc.0;
+        assert!(
+            encoded
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_.,=()[]-%".contains(c)),
+            "{encoded}"
+        );
+        assert!(encoded.contains("%20") && encoded.contains("%22"));
+        assert_eq!(percent_decode(&encoded).as_deref(), Some(satn.as_str()));
+
+        assert_eq!(percent_decode("%2"), None);
+        assert_eq!(percent_decode("%zz"), None);
+        assert_eq!(percent_decode("%+1"), None);
+    }
+
+    #[test]
+    fn digit_grouping() {
+        let grouped = Options {
+            digit_grouping: Some('_'),
+            ..Options::default()
+        };
+
+        assert_eq!(1234567u32.to_satn(), "1234567");
+        assert_eq!(1234567u32.to_satn_with(&grouped), "1_234_567");
+        assert_eq!(123u32.to_satn_with(&grouped), "123");
+        assert_eq!((-1234i32).to_satn_with(&grouped), "-1_234");
+        assert_eq!((-123i32).to_satn_with(&grouped), "-123");
+        assert_eq!(
+            i256::MIN.to_satn_with(&grouped),
+            "-57_896_044_618_658_097_711_785_492_504_343_953_926_634_992_332_820_282_019_728_792_003_956_564_819_968"
+        );
+        assert_eq!(
+            u256::MAX.to_satn_with(&grouped),
+            "115_792_089_237_316_195_423_570_985_008_687_907_853_269_984_665_640_564_039_457_584_007_913_129_639_935"
+        );
+    }
+
+    #[test]
+    fn hex_int_radix() {
+        let hex = Options {
+            int_radix: IntRadix::Hex,
+            ..Options::default()
+        };
+
+        assert_eq!(42u16.to_satn(), "42");
+        assert_eq!(42u8.to_satn_with(&hex), "0x2a");
+        assert_eq!(42u16.to_satn_with(&hex), "0x002a");
+        assert_eq!(0xdead_beefu32.to_satn_with(&hex), "0xdeadbeef");
+        assert_eq!(1u64.to_satn_with(&hex), "0x0000000000000001");
+        assert_eq!(u128::MAX.to_satn_with(&hex), format!("0x{}", "f".repeat(32)));
+        assert_eq!(u256::ONE.to_satn_with(&hex), format!("0x{}1", "0".repeat(63)));
+        // Signed integers render as their two's complement.
+        assert_eq!((-42i16).to_satn(), "-42");
+        assert_eq!((-42i16).to_satn_with(&hex), "0xffd6");
+        assert_eq!((-1i8).to_satn_with(&hex), "0xff");
+        assert_eq!(i32::MIN.to_satn_with(&hex), "0x80000000");
+        assert_eq!(i64::MAX.to_satn_with(&hex), "0x7fffffffffffffff");
+        assert_eq!((-1i128).to_satn_with(&hex), format!("0x{}", "f".repeat(32)));
+        assert_eq!(i256::MINUS_ONE.to_satn_with(&hex), format!("0x{}", "f".repeat(64)));
+
+        let grouped = Options {
+            digit_grouping: Some('_'),
+            ..hex
+        };
+        assert_eq!(0xdead_beefu32.to_satn_with(&grouped), "0xdead_beef");
+        assert_eq!(42u8.to_satn_with(&grouped), "0x2a");
+        // Floats are not affected.
+        assert_eq!(1.5f64.to_satn_with(&hex), "1.5");
+    }
+
+    #[test]
+    fn multiline_strings() {
+        let multiline = Options {
+            multiline_strings: true,
+            ..Options::default()
+        };
+        let value = product!["first\nsecond", 1u32];
+
+        assert_eq!(
+            value.to_satn_pretty_with(&multiline),
+            "(\n    0 = \"first\n        second\",\n    1 = 1,\n)"
+        );
+
+        // Compact renderings are unaffected.
+        assert_eq!(value.to_satn_with(&multiline), value.to_satn());
+        let escaped = Options {
+            escape_newlines: true,
+            ..multiline
+        };
+        assert_eq!(value.to_satn_with(&escaped), r#"(0 = "first\nsecond", 1 = 1)"#);
+        assert_eq!(
+            value.to_satn_pretty_with(&escaped),
+            value.to_satn_pretty_with(&multiline)
+        );
+        assert_eq!(
+            value.to_satn_pretty_with(&Options {
+                escape_newlines: true,
+                ..Options::default()
+            }),
+            "(\n    0 = \"first\\nsecond\",\n    1 = 1,\n)"
+        );
+    }
+
+    #[test]
+    fn compact_within_pretty() {
+        let map = BTreeMap::from([("a".to_string(), Compact(vec![1u32, 2, 3]))]);
+        let expected = [
+            "[",
+            "    (",
+            "        key = \"a\",",
+            "        value = [1, 2, 3],",
+            "    ),",
+            "]",
+        ];
+        assert_eq!(
+            SortedMap::new(&map).to_satn_pretty().lines().collect::<Vec<_>>(),
+            expected
+        );
+
+        let map = BTreeMap::from([("a".to_string(), Pretty(vec![1u32]))]);
+        assert_eq!(SortedMap::new(&map).to_satn(), "[(key = \"a\", value = [\n    1,\n])]");
+    }
 
-/// Provides the data format for named products for `SQL`.
-struct PsqlNamedFormatter<'a, 'f> {
-    /// The formatter for each element separating elements by a `,`.
-    f: EntryWrapper<'a, 'f, ','>,
-    /// The index of the element.
-    idx: usize,
-    /// If is not [Self::is_bytes_or_special] to control if we start with `(`
-    start: bool,
-    /// For checking [Self::is_bytes_or_special]
-    ty: &'a ProductType,
-    /// If the current element is a special type.
-    is_special: bool,
-}
+    /// A test-only product without fields, serialized as a seq or named product.
+    struct Empty {
+        named: bool,
+    }
 
-impl ser::SerializeNamedProduct for PsqlNamedFormatter<'_, '_> {
-    type Ok = ();
-    type Error = SatnError;
+    impl Serialize for Empty {
+        fn serialize<S: ser::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+            use ser::{SerializeNamedProduct as _, SerializeSeqProduct as _};
 
-    fn serialize_element<T: Satn + ser::Serialize + ?Sized>(
-        &mut self,
-        name: Option<&str>,
-        elem: &T,
-    ) -> Result<(), Self::Error> {
-        // For binary data, output in `hex` format and skip the tagging of each value
-        self.is_special = ProductType::is_special_tag(name.unwrap_or_default());
-        self.f.entry(|mut f| {
-            if !self.is_special {
-                if self.start {
-                    write!(f, "(")?; // Closed v
-                    self.start = false;
-                }
-                // Format the name or use the index if unnamed.
-                if let Some(name) = name {
-                    write!(f, "{}", name)?;
-                } else {
-                    write!(f, "{}", self.idx)?;
-                }
-                write!(f, " = ")?;
+            if self.named {
+                ser.serialize_named_product(0)?.end()
+            } else {
+                ser.serialize_seq_product(0)?.end()
             }
+        }
+    }
 
-            elem.serialize(PsqlFormatter {
-                fmt: SatnFormatter { f },
-                ty: self.ty,
-            })?;
+    #[test]
+    fn empty_products() {
+        let canonical = Options {
+            canonical: true,
+            ..Options::default()
+        };
+        for named in [false, true] {
+            let empty = Empty { named };
+            assert_eq!(empty.to_satn(), "()");
+            assert_eq!(empty.to_satn_pretty(), "()");
+            assert_eq!(empty.to_satn_with(&canonical), "()");
+            assert_eq!(empty.to_satn_pretty_with(&canonical), "()");
+        }
+        assert_eq!(ProductValue::default().to_satn(), "()");
+        assert_eq!(AlgebraicValue::unit().to_satn_pretty(), "()");
+    }
 
-            if !self.is_special {
-                self.idx += 1;
-            }
-            Ok(())
-        })?;
+    #[test]
+    fn key_cases() {
+        let value = product![1u32, product![2u32]];
+        let ty = ProductType::from([
+            ("my_field_name", AlgebraicType::U32),
+            ("nested", AlgebraicType::product([AlgebraicType::U32])),
+        ]);
+        let render = |key_case| {
+            let opts = Options {
+                key_case,
+                ..Options::default()
+            };
+            WithTypespace::empty(&ty).with_value(&value).to_satn_with(&opts)
+        };
+        assert_eq!(render(KeyCase::AsIs), "(my_field_name = 1, nested = (0 = 2))");
+        assert_eq!(render(KeyCase::Snake), "(my_field_name = 1, nested = (0 = 2))");
+        assert_eq!(render(KeyCase::Camel), "(myFieldName = 1, nested = (0 = 2))");
 
-        Ok(())
+        assert_eq!(KeyCase::Snake.apply("myFieldName"), "my_field_name");
+        assert_eq!(KeyCase::Snake.apply("ID"), "id");
+        assert_eq!(KeyCase::Camel.apply("_private_field"), "_privateField");
+        assert_eq!(KeyCase::Camel.apply("myFieldName"), "myFieldName");
     }
 
-    fn end(mut self) -> Result<Self::Ok, Self::Error> {
-        if !self.is_special {
-            write!(self.f.fmt, ")")?;
+    #[test]
+    fn blob_hash_threshold() {
+        let opts = Options {
+            blob_hash_threshold: Some(4),
+            ..Options::default()
+        };
+        let small = AlgebraicValue::Bytes([1, 2, 3, 4].into());
+        assert_eq!(small.to_satn_with(&opts), "0x01020304");
+
+        let large = AlgebraicValue::Bytes(vec![7u8; 1024 * 1024].into());
+        #[cfg(feature = "blake3")]
+        {
+            let hash = blake3::hash(large.as_bytes().unwrap());
+            assert_eq!(large.to_satn_with(&opts), format!("blob(len=1048576, blake3={hash})"));
         }
-        Ok(())
+        // Without the feature, the threshold is ignored.
+        #[cfg(not(feature = "blake3"))]
+        assert_eq!(large.to_satn_with(&opts), large.to_satn());
     }
-}
 
-/// Provides the data format for unnamed products for `SQL`.
-struct PsqlSeqFormatter<'a, 'f> {
-    /// Delegates to the named format.
-    inner: PsqlNamedFormatter<'a, 'f>,
-}
+    #[test]
+    fn type_name_prefix() {
+        let ty = ProductType::from([("id", AlgebraicType::U32), ("name", AlgebraicType::String)]);
+        let value = product![1u32, "x"];
+        let value = WithTypespace::empty(&ty).with_value(&value);
 
-impl ser::SerializeSeqProduct for PsqlSeqFormatter<'_, '_> {
-    type Ok = ();
-    type Error = SatnError;
+        let named = NamedSatnWrapper { name: "User", value };
+        assert_eq!(named.to_string(), r#"User(id = 1, name = "x")"#);
+        assert_eq!(format!("{named:#}"), "User(\n    id = 1,\n    name = \"x\",\n)");
 
-    fn serialize_element<T: ser::Serialize + ?Sized>(&mut self, elem: &T) -> Result<(), Self::Error> {
-        ser::SerializeNamedProduct::serialize_element(&mut self.inner, None, elem)
+        let quoted = NamedSatnWrapper {
+            name: "user table",
+            value,
+        };
+        assert_eq!(quoted.to_string(), r#""user table"(id = 1, name = "x")"#);
+
+        let escaped = NamedSatnWrapper {
+            name: r#"the "users" \ table"#,
+            value,
+        };
+        assert_eq!(escaped.to_string(), r#""the \"users\" \\ table"(id = 1, name = "x")"#);
     }
 
-    fn end(self) -> Result<Self::Ok, Self::Error> {
-        ser::SerializeNamedProduct::end(self.inner)
+    #[test]
+    fn document_separates_values() {
+        let values = [product![1u32], product![2u32], product![3u32]];
+        let doc = DocumentFormatter::new(1).render(&values);
+        assert_eq!(doc, "(\n    0 = 1,\n)\n\n(\n    0 = 2,\n)\n\n(\n    0 = 3,\n)",);
+        assert_eq!(doc.split("\n\n").count(), 3);
+
+        let dense = DocumentFormatter::new(0).with_options(Options {
+            trailing_newline: true,
+            ..Options::default()
+        });
+        assert_eq!(dense.render(&values[..2]), "(\n    0 = 1,\n)\n(\n    0 = 2,\n)\n");
+        assert_eq!(dense.render(Vec::<u32>::new()), "\n");
     }
-}
 
-/// An implementation of [`Serializer`](ser::Serializer) for `SQL` output.
-struct PsqlFormatter<'a, 'f> {
-    fmt: SatnFormatter<'a, 'f>,
-    ty: &'a ProductType,
-}
+    #[test]
+    fn ndsatn_is_line_delimited() {
+        let rows = [
+            product![1u32, "one"],
+            product![2u32, "two\nlines"],
+            product![3u32, "\r\n"],
+        ];
+        let mut out = Vec::new();
+        write_ndsatn(&rows, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
 
-impl<'a, 'f> ser::Serializer for PsqlFormatter<'a, 'f> {
-    type Ok = ();
-    type Error = SatnError;
-    type SerializeArray = ArrayFormatter<'a, 'f>;
-    type SerializeSeqProduct = PsqlSeqFormatter<'a, 'f>;
-    type SerializeNamedProduct = PsqlNamedFormatter<'a, 'f>;
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                r#"(0 = 1, 1 = "one")"#,
+                r#"(0 = 2, 1 = "two\nlines")"#,
+                r#"(0 = 3, 1 = "\r\n")"#,
+            ]
+        );
+        assert!(out.ends_with(")\n"));
+    }
 
-    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_bool(v)
+    #[test]
+    fn sql_literals() {
+        assert_eq!(to_sql_literal("it's").unwrap(), "'it''s'");
+        assert_eq!(to_sql_literal("''").unwrap(), "''''''");
+        assert_eq!(to_sql_literal(&b"\x01\xab"[..]).unwrap(), r"'\x01ab'");
+        assert_eq!(to_sql_literal(&true).unwrap(), "TRUE");
+        assert_eq!(to_sql_literal(&false).unwrap(), "FALSE");
+        assert_eq!(to_sql_literal(&None::<u32>).unwrap(), "NULL");
+        assert_eq!(to_sql_literal(&Some("x")).unwrap(), "'x'");
+        assert_eq!(to_sql_literal(&-42i64).unwrap(), "-42");
+        assert_eq!(to_sql_literal(&f64::NAN).unwrap(), "'NaN'");
+
+        // Options without a static type are recognized via the typespace.
+        let ty = AlgebraicType::option(AlgebraicType::String);
+        let none = AlgebraicValue::OptionNone();
+        assert_eq!(
+            to_sql_literal(&WithTypespace::empty(&ty).with_value(&none)).unwrap(),
+            "NULL"
+        );
+
+        assert!(matches!(to_sql_literal("a\0b"), Err(SqlLiteralError::NulInString)));
+        assert!(matches!(
+            to_sql_literal(&vec![1u32]),
+            Err(SqlLiteralError::Unsupported(_))
+        ));
+        assert!(matches!(
+            to_sql_literal(&product![1u32]),
+            Err(SqlLiteralError::Unsupported(_))
+        ));
     }
-    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_u8(v)
+
+    #[test]
+    fn tokenizer_events() {
+        let ty = ProductType::from([
+            ("a", AlgebraicType::U32),
+            (
+                "b",
+                AlgebraicType::product([
+                    ("c", AlgebraicType::String),
+                    ("d", AlgebraicType::array(AlgebraicType::U8)),
+                ]),
+            ),
+            ("e", AlgebraicType::option(AlgebraicType::Bool)),
+        ]);
+        let value = product![
+            1u32,
+            product!["x", AlgebraicValue::Bytes([7u8].into())],
+            AlgebraicValue::OptionSome(AlgebraicValue::Bool(true))
+        ];
+
+        let events = SatnTokenizer::new(&WithTypespace::empty(&ty).with_value(&value)).collect::<Vec<_>>();
+        let field = |name: &str| SatnEvent::Field(Some(name.into()));
+        assert_eq!(
+            events,
+            [
+                SatnEvent::BeginProduct,
+                field("a"),
+                SatnEvent::Scalar(AlgebraicValue::U32(1)),
+                field("b"),
+                SatnEvent::BeginProduct,
+                field("c"),
+                SatnEvent::Scalar(AlgebraicValue::String("x".into())),
+                field("d"),
+                SatnEvent::Scalar(AlgebraicValue::Bytes([7u8].into())),
+                SatnEvent::EndProduct,
+                field("e"),
+                SatnEvent::BeginVariant {
+                    tag: 0,
+                    name: Some("some".into()),
+                },
+                SatnEvent::Scalar(AlgebraicValue::Bool(true)),
+                SatnEvent::EndVariant,
+                SatnEvent::EndProduct,
+            ]
+        );
+
+        let events = SatnTokenizer::new(&vec![product![1u32]]).collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                SatnEvent::BeginArray,
+                SatnEvent::BeginProduct,
+                SatnEvent::Field(None),
+                SatnEvent::Scalar(AlgebraicValue::U32(1)),
+                SatnEvent::EndProduct,
+                SatnEvent::EndArray,
+            ]
+        );
     }
-    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_u16(v)
+
+    #[test]
+    fn columnar_header_and_rows() {
+        let ty = ProductType::from([
+            (Some("id"), AlgebraicType::U32),
+            (Some("a, b"), AlgebraicType::String),
+            (None, AlgebraicType::option(AlgebraicType::U32)),
+        ]);
+        let rows = [
+            product![1u32, "ann", AlgebraicValue::OptionSome(AlgebraicValue::U32(7))],
+            product![2u32, r#"bob, "bobby""#, AlgebraicValue::OptionNone()],
+            product![3u32, "two\nlines", AlgebraicValue::OptionSome(AlgebraicValue::U32(9))],
+        ];
+        let mut out = Vec::new();
+        write_columnar_satn(&ty, &rows, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                r#"("id", "a, b", 2)"#,
+                r#"(1, "ann", (some = 7))"#,
+                r#"(2, "bob, \"bobby\"", (none = ()))"#,
+                r#"(3, "two\nlines", (some = 9))"#,
+            ]
+        );
+
+        /// Splits the fields of a rendered product at its top-level commas,
+        /// skipping over strings and nested sections.
+        fn fields(line: &str) -> Vec<&str> {
+            let inner = line.strip_prefix('(').unwrap().strip_suffix(')').unwrap();
+            let (mut fields, mut start, mut depth) = (Vec::new(), 0, 0);
+            let (mut in_string, mut escaped) = (false, false);
+            for (i, c) in inner.char_indices() {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' if in_string => escaped = true,
+                    '"' => in_string = !in_string,
+                    _ if in_string => {}
+                    '(' | '[' | '{' => depth += 1,
+                    ')' | ']' | '}' => depth -= 1,
+                    ',' if depth == 0 => {
+                        fields.push(&inner[start..i]);
+                        start = i + 2;
+                    }
+                    _ => {}
+                }
+            }
+            fields.push(&inner[start..]);
+            fields
+        }
+        /// Parses a field name of the header.
+        fn name(field: &str) -> String {
+            let Some(quoted) = field.strip_prefix('"').and_then(|f| f.strip_suffix('"')) else {
+                return field.to_owned();
+            };
+            let mut name = String::new();
+            let mut chars = quoted.chars();
+            while let Some(c) = chars.next() {
+                name.push(if c == '\\' { chars.next().unwrap() } else { c });
+            }
+            name
+        }
+
+        // The header provides the names to recover the named rendering of each row.
+        let names = fields(lines[0]).into_iter().map(name).collect::<Vec<_>>();
+        assert_eq!(names, ["id", "a, b", "2"]);
+        let opts = Options {
+            escape_newlines: true,
+            string_quote: Some('"'),
+            ..Options::default()
+        };
+        for (line, row) in lines[1..].iter().zip(&rows) {
+            let values = fields(line);
+            assert_eq!(values.len(), names.len());
+            let named = names
+                .iter()
+                .zip(values)
+                .map(|(name, value)| format!("{name} = {value}"));
+            let named = format!("({})", named.collect::<Vec<_>>().join(", "));
+            assert_eq!(named, WithTypespace::empty(&ty).with_value(row).to_satn_with(&opts));
+        }
+
+        let short = product![1u32];
+        let err = write_columnar_satn(&ty, [&short], &mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
     }
-    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_u32(v)
+
+    #[test]
+    fn index_array_elements() {
+        let opts = Options {
+            index_array_elements: true,
+            ..Options::default()
+        };
+        let value = vec!["a", "b", "c"];
+
+        assert_eq!(
+            value.to_satn_pretty_with(&opts),
+            "[\n    [0]: \"a\",\n    [1]: \"b\",\n    [2]: \"c\",\n]"
+        );
+        assert_eq!(value.to_satn_with(&opts), value.to_satn());
     }
-    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_u64(v)
+
+    #[test]
+    fn string_max_len() {
+        let opts = Options {
+            string_max_len: Some(8),
+            ..Options::default()
+        };
+
+        assert_eq!("short".to_satn_with(&opts), r#""short""#);
+        assert_eq!("exactly8".to_satn_with(&opts), r#""exactly8""#);
+
+        // Each `ä` is two bytes, so the limit falls in the middle of the fifth.
+        let long = "ääääää";
+        assert_eq!(long.len(), 12);
+        assert_eq!(long.to_satn_with(&opts), r#""ääää…(+4)""#);
+
+        let value = product![long, 1u32];
+        assert_eq!(value.to_satn_with(&opts), r#"(0 = "ääää…(+4)", 1 = 1)"#);
+        assert_eq!(value.to_satn(), r#"(0 = "ääääää", 1 = 1)"#);
     }
-    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_u128(v)
+
+    #[test]
+    fn trailing_newline() {
+        let opts = Options {
+            trailing_newline: true,
+            ..Options::default()
+        };
+        let value = product![1u32, product!["a", "b"]];
+
+        let compact = value.to_satn_with(&opts);
+        assert_eq!(compact.strip_suffix('\n').unwrap(), value.to_satn());
+        let pretty = value.to_satn_pretty_with(&opts);
+        assert_eq!(pretty.strip_suffix('\n').unwrap(), value.to_satn_pretty());
+        assert!(!value.to_satn_pretty().ends_with('\n'));
+
+        struct WithOpts<'a>(&'a ProductValue, Options);
+        impl fmt::Display for WithOpts<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt_with(f, &self.1)
+            }
+        }
+        let wrapped = WithOpts(&value, opts);
+        assert_eq!(format!("{wrapped}"), compact);
+        assert_eq!(format!("{wrapped:#}"), pretty);
     }
-    fn serialize_u256(self, v: u256) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_u256(v)
+
+    #[test]
+    fn bare_scalars() {
+        let bare = Options {
+            bare_scalar: true,
+            ..Options::default()
+        };
+        assert_eq!("it's\n\"here\"".to_satn_with(&bare), "it's\\n\"here\"");
+        assert_eq!("it's".to_satn(), "\"it's\"");
+        assert_eq!(42u64.to_satn_with(&bare), "42");
+
+        // Nested strings stay quoted.
+        assert_eq!(vec!["a"].to_satn_with(&bare), "[\"a\"]");
+        let value = product!["a"];
+        assert_eq!(value.to_satn_with(&bare), "(0 = \"a\")");
     }
-    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_i8(v)
+
+    #[test]
+    fn string_quotes() {
+        let s = r#"say "it's" \o/"#;
+        // By default, strings are not escaped.
+        assert_eq!(s.to_satn(), r#""say "it's" \o/""#);
+        let double = Options {
+            string_quote: Some('"'),
+            ..Options::default()
+        };
+        assert_eq!(s.to_satn_with(&double), r#""say \"it's\" \\o/""#);
+        let single = Options {
+            string_quote: Some('\''),
+            ..Options::default()
+        };
+        assert_eq!(s.to_satn_with(&single), r#"'say "it\'s" \\o/'"#);
+        assert_eq!("".to_satn_with(&single), "''");
+        assert_eq!("plain".to_satn(), r#""plain""#);
+
+        // Byte literals use the same delimiter and escaping.
+        let text = Options {
+            bytes_style: BytesStyle::TextOrHex,
+            ..single
+        };
+        let bytes = AlgebraicValue::Bytes(s.as_bytes().into());
+        assert_eq!(bytes.to_satn_with(&text), r#"t'say "it\'s" \\o/'"#);
     }
-    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_i16(v)
+
+    #[test]
+    fn integer_extremes_across_widths() {
+        fn check(s: String, expected: String, signed: bool) {
+            assert_eq!(s, expected);
+            let digits = s.strip_prefix('-').filter(|_| signed).unwrap_or(&s);
+            assert!(digits.bytes().all(|b| b.is_ascii_digit()), "{s}");
+            assert!(digits == "0" || !digits.starts_with('0'), "{s}");
+        }
+        macro_rules! signed {
+            ($($t:ty),*) => {$(
+                for v in [<$t>::MIN, <$t>::MAX, <$t>::from(0i8), <$t>::from(-1i8)] {
+                    check(v.to_satn(), v.to_string(), true);
+                }
+                assert_eq!(<$t>::from(-1i8).to_satn(), "-1");
+                assert_eq!(<$t>::from(0i8).to_satn(), "0");
+            )*};
+        }
+        macro_rules! unsigned {
+            ($($t:ty),*) => {$(
+                for v in [<$t>::MIN, <$t>::MAX] {
+                    check(v.to_satn(), v.to_string(), false);
+                }
+                assert_eq!(<$t>::MIN.to_satn(), "0");
+            )*};
+        }
+        signed!(i8, i16, i32, i64, i128, i256);
+        unsigned!(u8, u16, u32, u64, u128, u256);
+        // Negative zero only exists for floats, where it is kept distinct.
+        assert_eq!((-0.0f64).to_satn(), "-0");
+        assert_eq!(0.0f64.to_satn(), "0");
+
+        assert_eq!(i8::MIN.to_satn(), "-128");
+        assert_eq!(i128::MIN.to_satn(), "-170141183460469231731687303715884105728");
+        assert_eq!(
+            i256::MIN.to_satn(),
+            "-57896044618658097711785492504343953926634992332820282019728792003956564819968"
+        );
+        assert_eq!(
+            i256::MAX.to_satn(),
+            "57896044618658097711785492504343953926634992332820282019728792003956564819967"
+        );
+        assert_eq!(
+            u256::MAX.to_satn(),
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+        );
     }
-    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_i32(v)
+
+    #[test]
+    fn numbers_are_locale_independent() {
+        // `core::fmt` never consults the C locale, so these hold regardless of
+        // what `setlocale` was called with, e.g. by a library via FFI.
+        for v in [1234567.5, -0.001, 1e-7, 1e21, f64::MAX, f64::MIN_POSITIVE] {
+            let s = v.to_satn();
+            assert!(s.bytes().all(|b| b.is_ascii_digit() || b == b'.' || b == b'-'), "{s}");
+            assert_eq!(s.parse::<f64>().unwrap(), v);
+        }
+        assert_eq!(1234567.5f64.to_satn(), "1234567.5");
+        assert_eq!((-0.25f32).to_satn(), "-0.25");
+        assert_eq!(1234567u64.to_satn(), "1234567");
+        assert_eq!((-1234567i128).to_satn(), "-1234567");
+        assert_eq!(f64::NAN.to_satn(), "NaN");
+        assert_eq!(f64::NEG_INFINITY.to_satn(), "-inf");
+
+        // Grouping only happens when requested, and never applies to floats.
+        let grouped = Options {
+            digit_grouping: Some('_'),
+            ..Options::default()
+        };
+        assert_eq!(1234567u64.to_satn_with(&grouped), "1_234_567");
+        assert_eq!(1234567.5f64.to_satn_with(&grouped), "1234567.5");
     }
-    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_i64(v)
+
+    #[test]
+    fn float_rendering_is_stable() {
+        // Golden renderings, see `write_float`. Don't update these to make the
+        // test pass, restore the rendering instead.
+        let zeros = |n| "0".repeat(n);
+        let f64s = [
+            (1.0, "1".to_owned()),
+            (-3.0, "-3".to_owned()),
+            (9007199254740992.0, "9007199254740992".to_owned()),
+            (0.0, "0".to_owned()),
+            (-0.0, "-0".to_owned()),
+            (0.1, "0.1".to_owned()),
+            (1.0 / 3.0, "0.3333333333333333".to_owned()),
+            (1e21, format!("1{}", zeros(21))),
+            (1e300, format!("1{}", zeros(300))),
+            (f64::MAX, format!("17976931348623157{}", zeros(292))),
+            (1e-7, "0.0000001".to_owned()),
+            (f64::MIN_POSITIVE, format!("0.{}22250738585072014", zeros(307))),
+            (5e-324, format!("0.{}5", zeros(323))),
+            (f64::NAN, "NaN".to_owned()),
+            (f64::INFINITY, "inf".to_owned()),
+            (f64::NEG_INFINITY, "-inf".to_owned()),
+        ];
+        for (v, expected) in f64s {
+            assert_eq!(v.to_satn(), expected, "{v:e}");
+        }
+
+        let f32s = [
+            (1.0f32, "1".to_owned()),
+            (-0.0, "-0".to_owned()),
+            (0.1, "0.1".to_owned()),
+            (16777216.0, "16777216".to_owned()),
+            (1e38, format!("1{}", zeros(38))),
+            (1e-45, format!("0.{}1", zeros(44))),
+            (f32::NAN, "NaN".to_owned()),
+        ];
+        for (v, expected) in f32s {
+            assert_eq!(v.to_satn(), expected, "{v:e}");
+        }
     }
-    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_i128(v)
+
+    #[test]
+    fn delta_renders_differences() {
+        let ty = ProductType::from([
+            ("name", AlgebraicType::String),
+            (
+                "cfg",
+                AlgebraicType::product([("port", AlgebraicType::U16), ("host", AlgebraicType::String)]),
+            ),
+            ("tags", AlgebraicType::array(AlgebraicType::U32)),
+        ]);
+        let config = |port: u16, tags: &[u32]| {
+            AlgebraicValue::product([
+                AlgebraicValue::String("db".into()),
+                AlgebraicValue::product([AlgebraicValue::U16(port), AlgebraicValue::String("localhost".into())]),
+                AlgebraicValue::Array(crate::ArrayValue::U32(tags.into())),
+            ])
+        };
+        let ty = AlgebraicType::Product(ty);
+        let typed = |value| WithTypespace::empty(&ty).with_value(value);
+        let (base, new) = (config(80, &[1, 2]), config(8080, &[1, 2]));
+
+        assert_eq!(
+            delta(&typed(&base), &typed(&new)),
+            "(name = <unchanged>, cfg = (port = 8080, host = <unchanged>), tags = <unchanged>)"
+        );
+        assert_eq!(delta_with(&typed(&base), &typed(&new), true), "(cfg = (port = 8080))");
+        assert_eq!(delta(&typed(&base), &typed(&base)), "<unchanged>");
+
+        let new = config(80, &[1, 5, 3]);
+        assert_eq!(
+            delta(&typed(&base), &typed(&new)),
+            "(name = <unchanged>, cfg = <unchanged>, tags = [<unchanged>, 5, 3])"
+        );
+        assert_eq!(
+            delta_with(&typed(&base), &typed(&new), true),
+            "(tags = [[1]: 5, [2]: 3])"
+        );
     }
-    fn serialize_i256(self, v: i256) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_i256(v)
+
+    #[test]
+    fn padding_applies_to_scalars() {
+        assert_eq!(format!("{:>10}", Wrapper(&42u32)), "        42");
+        assert_eq!(format!("{:*<6}", Wrapper("ab")), "\"ab\"**");
+        assert_eq!(format!("{:^7}", Wrapper(&true)), " true  ");
+        assert_eq!(format!("{:>10}", Wrapper(&[1u32, 2])), "[1, 2]");
+        let value = AlgebraicValue::product([AlgebraicValue::U32(1)]);
+        assert_eq!(format!("{:>10}", Wrapper(&value)), "(0 = 1)");
     }
-    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_f32(v)
+
+    #[test]
+    fn padding_ignores_precision() {
+        assert_eq!(format!("{:>10.1}", Wrapper(&12345u32)), "     12345");
+        assert_eq!(format!("{:>10.2}", Wrapper(&1.2345f64)), "    1.2345");
+        assert_eq!(format!("{:>10.3}", Wrapper("hello")), "   \"hello\"");
+        assert_eq!(format!("{:<8.1}", Wrapper(&true)), "true    ");
     }
-    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_f64(v)
+
+    #[test]
+    fn framed_satn() {
+        let value = AlgebraicValue::product([AlgebraicValue::String("héllo".into()), AlgebraicValue::U32(42)]);
+        let framed = value.to_satn_framed();
+        let (prefix, body) = framed.split_at(4);
+        assert_eq!(u32::from_le_bytes(prefix.try_into().unwrap()) as usize, body.len());
+        assert_eq!(body, value.to_satn().as_bytes());
     }
 
-    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_str(v)
+    #[test]
+    fn depth_limited_debug() {
+        let value = vec![vec![vec![1u32, 2]], vec![]];
+        assert_eq!(format!("{}", Wrapper(&value)), "[[[1, 2]], []]");
+        assert_eq!(format!("{:?}", Wrapper(&value)), "[[[…]], []]");
+
+        let nested = Some(Some(Some(1u32)));
+        assert_eq!(format!("{:?}", Wrapper(&nested)), "(some = (some = (some = …)))");
+        let opts = Options {
+            max_depth: Some(1),
+            ..Options::default()
+        };
+        assert_eq!(value.to_satn_with(&opts), "[[…], []]");
+        let row = AlgebraicValue::product([AlgebraicValue::product([
+            AlgebraicValue::U32(1),
+            AlgebraicValue::U32(2),
+        ])]);
+        assert_eq!(row.to_satn_with(&opts), "(0 = (…))");
+        let opts = Options {
+            max_depth: Some(0),
+            variant_style: VariantStyle::Tag,
+            ..Options::default()
+        };
+        assert_eq!(nested.to_satn_with(&opts), "@0(…)");
+        // Scalars at the top level are never elided.
+        assert_eq!(format!("{:?}", Wrapper(&1u32)), "1");
     }
 
-    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_bytes(v)
+    #[test]
+    fn null_keyword() {
+        let ty = ProductType::from([("x", AlgebraicType::option(AlgebraicType::U32))]);
+        let row = product![AlgebraicValue::OptionNone()];
+        let value = WithTypespace::empty(&ty).with_value(&row);
+
+        struct Psql<'a, T>(&'a ProductType, T, Options);
+        impl<T: Satn> fmt::Display for Psql<'_, T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.1.fmt_psql_with(f, self.0, &self.2)
+            }
+        }
+
+        for keyword in ["null", "NULL", "nil"] {
+            let opts = Options {
+                null_keyword: Some(keyword),
+                ..Options::default()
+            };
+            assert_eq!(None::<u32>.to_satn_with(&opts), keyword);
+            assert_eq!(Some(1u32).to_satn_with(&opts), "(some = 1)");
+            assert_eq!(value.to_satn_with(&opts), format!("(x = {keyword})"));
+            assert_eq!(Psql(&ty, value, opts).to_string(), format!("(x = {keyword})"));
+        }
+        assert_eq!(None::<u32>.to_satn(), "(none = ())");
     }
 
-    fn serialize_array(self, len: usize) -> Result<Self::SerializeArray, Self::Error> {
-        self.fmt.serialize_array(len)
+    #[test]
+    fn psql_char_tag() {
+        let ty = ProductType::from([
+            ("c", AlgebraicType::product([(CHAR_TAG, AlgebraicType::U32)])),
+            ("n", AlgebraicType::U32),
+        ]);
+        let psql = |v: u32| {
+            let row = product![product![v], v];
+            WithTypespace::empty(&ty)
+                .with_values(&row)
+                .map(|value| PsqlWrapper { ty: &ty, value }.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(psql(65), ["'A'", "65"]);
+        assert_eq!(psql('€' as u32), ["'€'", "8364"]);
+        assert_eq!(psql('\'' as u32), [r"'\''", "39"]);
+        // Surrogates and out-of-range values are not chars.
+        assert_eq!(psql(0xD800), ["55296", "55296"]);
+        assert_eq!(psql(0x110000), ["1114112", "1114112"]);
     }
 
-    fn serialize_seq_product(self, len: usize) -> Result<Self::SerializeSeqProduct, Self::Error> {
-        Ok(PsqlSeqFormatter {
-            inner: self.serialize_named_product(len)?,
-        })
+    /// A test-only node of a linked list, which may be made cyclic.
+    struct Node {
+        value: u32,
+        next: RefCell<Option<Rc<Node>>>,
     }
 
-    fn serialize_named_product(self, _len: usize) -> Result<Self::SerializeNamedProduct, Self::Error> {
-        Ok(PsqlNamedFormatter {
-            f: EntryWrapper::new(self.fmt.f),
-            idx: 0,
-            start: true,
-            ty: self.ty,
-            is_special: false,
-        })
+    impl Serialize for Node {
+        fn serialize<S: ser::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+            use ser::SerializeNamedProduct as _;
+
+            let mut prod = ser.serialize_named_product(2)?;
+            prod.serialize_element(Some("value"), &self.value)?;
+            prod.serialize_element(Some("next"), &*self.next.borrow())?;
+            prod.end()
+        }
     }
 
-    fn serialize_variant<T: ser::Serialize + ?Sized>(
-        self,
-        tag: u8,
-        name: Option<&str>,
-        value: &T,
-    ) -> Result<Self::Ok, Self::Error> {
-        self.fmt.serialize_variant(tag, name, value)
+    #[test]
+    fn cycle_marker() {
+        let opts = Options {
+            detect_cycles: true,
+            ..Options::default()
+        };
+        let a = Rc::new(Node {
+            value: 1,
+            next: RefCell::new(None),
+        });
+        let b = Rc::new(Node {
+            value: 2,
+            next: RefCell::new(Some(a.clone())),
+        });
+
+        let acyclic = "(value = 2, next = (some = (value = 1, next = (none = ()))))";
+        assert_eq!(b.to_satn(), acyclic);
+        assert_eq!(b.to_satn_with(&opts), acyclic);
+
+        *a.next.borrow_mut() = Some(b.clone());
+        assert_eq!(
+            a.to_satn_with(&opts),
+            "(value = 1, next = (some = (value = 2, next = (some = (value = 1, next = <cycle>)))))"
+        );
+
+        // Break the cycle, so the nodes can be dropped.
+        a.next.borrow_mut().take();
     }
 
-    unsafe fn serialize_bsatn(self, ty: &crate::AlgebraicType, bsatn: &[u8]) -> Result<Self::Ok, Self::Error> {
-        // SAFETY: Forward caller requirements of this method to that we are calling.
-        unsafe { self.fmt.serialize_bsatn(ty, bsatn) }
+    /// Asserts that every `{len}` prefix in `out` covers exactly one balanced section.
+    fn assert_prefixes_match(out: &str) {
+        let mut sections = 0;
+        for (start, _) in out.match_indices('{') {
+            let close = start + out[start..].find('}').unwrap();
+            let len: usize = out[start + 1..close].parse().unwrap();
+            let section = &out[close + 1..close + 1 + len];
+            let closer = match section.as_bytes()[0] {
+                b'[' => b']',
+                b'(' => b')',
+                c => panic!("unexpected section start {:?} in {out:?}", c as char),
+            };
+            assert_eq!(section.as_bytes()[len - 1], closer, "section {section:?} in {out:?}");
+            let depth = section.bytes().fold(0i32, |depth, c| match c {
+                b'[' | b'(' => depth + 1,
+                b']' | b')' => depth - 1,
+                _ => depth,
+            });
+            assert_eq!(depth, 0, "unbalanced section {section:?} in {out:?}");
+            sections += 1;
+        }
+        assert!(sections > 0);
     }
 
-    unsafe fn serialize_bsatn_in_chunks<'c, I: Clone + Iterator<Item = &'c [u8]>>(
-        self,
-        ty: &crate::AlgebraicType,
-        total_bsatn_len: usize,
-        bsatn: I,
-    ) -> Result<Self::Ok, Self::Error> {
-        // SAFETY: Forward caller requirements of this method to that we are calling.
-        unsafe { self.fmt.serialize_bsatn_in_chunks(ty, total_bsatn_len, bsatn) }
+    #[test]
+    fn length_prefixed_sections() {
+        let map = BTreeMap::from([("a".to_string(), vec![1u32, 2]), ("b".to_string(), vec![])]);
+        let opts = Options {
+            length_prefixed: true,
+            ..Options::default()
+        };
+
+        let out = SortedMap::new(&map).to_satn_with(&opts);
+        assert_eq!(
+            out,
+            r#"{68}[{30}(key = "a", value = {6}[1, 2]), {26}(key = "b", value = {2}[])]"#
+        );
+        assert_prefixes_match(&out);
+
+        let pretty = SortedMap::new(&map).to_satn_pretty_with(&opts);
+        assert_prefixes_match(&pretty);
+        // Apart from the prefixes, the output is unchanged.
+        assert_eq!(strip_prefixes(&pretty), SortedMap::new(&map).to_satn_pretty());
+
+        // Reordered fields are rendered before being written out.
+        let value = product![product![1u32, 2u32], 3u32];
+        let ty = ProductType::from([
+            (
+                "b",
+                AlgebraicType::product([("x", AlgebraicType::U32), ("y", AlgebraicType::U32)]),
+            ),
+            ("a", AlgebraicType::U32),
+        ]);
+        let value = WithTypespace::empty(&ty).with_value(&value);
+        let canonical = Options {
+            canonical: true,
+            ..Options::default()
+        };
+        let field_order = Options {
+            field_order: &["a", "b"],
+            ..Options::default()
+        };
+        for unprefixed in [canonical, field_order] {
+            let opts = Options {
+                length_prefixed: true,
+                ..unprefixed
+            };
+            assert_eq!(value.to_satn_with(&opts), "{31}(a = 3, b = {14}(x = 1, y = 2))");
+            let pretty = value.to_satn_pretty_with(&opts);
+            assert_eq!(
+                pretty,
+                "{65}(\n    a = 3,\n    b = {37}(\n        x = 1,\n        y = 2,\n    ),\n)"
+            );
+            assert_prefixes_match(&pretty);
+            assert_eq!(strip_prefixes(&pretty), value.to_satn_pretty_with(&unprefixed));
+        }
     }
 
-    unsafe fn serialize_str_in_chunks<'c, I: Clone + Iterator<Item = &'c [u8]>>(
-        self,
-        total_len: usize,
-        string: I,
-    ) -> Result<Self::Ok, Self::Error> {
-        // SAFETY: Forward caller requirements of this method to that we are calling.
-        unsafe { self.fmt.serialize_str_in_chunks(total_len, string) }
+    /// Removes every `{len}` prefix from `out`.
+    fn strip_prefixes(out: &str) -> String {
+        let mut rest = out;
+        let mut stripped = String::new();
+        while let Some(start) = rest.find('{') {
+            stripped.push_str(&rest[..start]);
+            rest = &rest[start + rest[start..].find('}').unwrap() + 1..];
+        }
+        stripped.push_str(rest);
+        stripped
     }
 }
@@ -195,6 +195,26 @@ pub trait Serializer: Sized {
         total_len: usize,
         string: I,
     ) -> Result<Self::Ok, Self::Error>;
+
+    /// Serialize `value`, laid out according to `layout` if the format supports it.
+    ///
+    /// The layout is a presentation hint for human-readable formats, e.g. SATN,
+    /// and must not affect the serialized data.
+    /// The default implementation ignores it.
+    fn serialize_with_layout<T: Serialize + ?Sized>(self, layout: Layout, value: &T) -> Result<Self::Ok, Self::Error> {
+        let _ = layout;
+        value.serialize(self)
+    }
+}
+
+/// A layout of a value in human-readable formats,
+/// see [`Serializer::serialize_with_layout`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// Everything on one line.
+    Compact,
+    /// Indented, with one entry per line.
+    Pretty,
 }
 
 use ethnum::{i256, u256};